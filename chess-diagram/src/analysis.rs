@@ -0,0 +1,103 @@
+use shakmaty::{Chess, Color, Position};
+use shakmaty_uci::UciMove;
+use ucui_engine::{Score, SearchInfo};
+use ucui_utils::{move_classic_to, ucimovelist_to_sanlist};
+
+use crate::board::Arrow;
+
+const MATE_VALUE: i32 = 100_000;
+
+/// A line of moves from `game`, numbered as in "12.Nf3 e5 13.d4 …"
+pub fn pv_text(game: Chess, pv: &[UciMove]) -> String {
+    let start = game.fullmoves().get();
+    let sanlist = if game.turn() == Color::Black {
+        let mut sanlist = vec![String::from("…")];
+        sanlist.extend(ucimovelist_to_sanlist(game, pv));
+        sanlist
+    } else {
+        ucimovelist_to_sanlist(game, pv)
+    };
+    let moves: Vec<String> = sanlist
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let number = start.saturating_add(i as u32);
+            match (pair.first(), pair.get(1)) {
+                (Some(a), Some(b)) => format!("{number}.{a} {b}"),
+                (Some(a), None) => format!("{number}.{a} …"),
+                _ => String::from("??"),
+            }
+        })
+        .collect();
+    moves.join("  ")
+}
+
+/// A score from White's point of view, engines give it for the side to move.
+pub fn score_text(score: &Score, turn: Color) -> Option<String> {
+    let sign = if turn == Color::White { 1 } else { -1 };
+    match score {
+        Score::CentiPawns { score, .. } => Some(format!("{:+.2}", (score * sign) as f32 / 100.0)),
+        Score::Mate { moves } => Some(format!("#{}", *moves as i32 * sign)),
+        Score::None => None,
+    }
+}
+
+/// A score as centipawns for the side to move, mates being worth more
+/// the sooner they come.
+pub fn score_value(score: &Score) -> Option<i32> {
+    match score {
+        Score::CentiPawns { score, .. } => Some(*score),
+        Score::Mate { moves } if *moves > 0 => Some(MATE_VALUE - *moves as i32),
+        Score::Mate { moves } => Some(-MATE_VALUE - *moves as i32),
+        Score::None => None,
+    }
+}
+
+/// The first move of each line, fading as they get worse than the best one.
+pub fn candidate_arrows(game: &Chess, lines: &[SearchInfo]) -> Vec<Arrow> {
+    let best = lines.first().and_then(|line| score_value(&line.score));
+    lines
+        .iter()
+        .filter_map(|line| {
+            let move_ = line.pv.first()?.to_move(game).ok()?;
+            let loss = best
+                .zip(score_value(&line.score))
+                .map(|(best, value)| (best - value).max(0))
+                .unwrap_or(0);
+            Some(Arrow {
+                from: move_.from()?,
+                to: move_classic_to(&move_),
+                weight: (-(loss as f32) / 100.0).exp().max(0.1),
+            })
+        })
+        .collect()
+}
+
+fn count_text(count: u64) -> String {
+    if count >= 1_000_000 {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    } else if count >= 1_000 {
+        format!("{:.1}k", count as f64 / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+/// One line summing up the running analysis of `game`
+pub fn analysis_text(game: &Chess, info: &SearchInfo) -> String {
+    let mut parts = Vec::new();
+    if let Some(depth) = info.depth {
+        parts.push(format!("depth {depth}"));
+    }
+    if let Some(score) = score_text(&info.score, game.turn()) {
+        parts.push(format!("[{score}]"));
+    }
+    parts.push(pv_text(game.clone(), &info.pv));
+    if let Some(nodes) = info.nodes {
+        parts.push(format!("{} nodes", count_text(nodes)));
+    }
+    if let Some(nps) = info.nps {
+        parts.push(format!("{} n/s", count_text(nps)));
+    }
+    parts.join("  ")
+}
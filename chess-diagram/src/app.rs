@@ -1,29 +1,46 @@
 use std::cell::RefCell;
-use std::fmt::format;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use egui::Key;
 use egui_extras::install_image_loaders;
 use log::info;
 use shakmaty::fen::Fen;
-use shakmaty::san::San;
-use shakmaty::{Chess, Color, Move, Position};
-use ucui_engine::Score;
-use ucui_utils::ucimovelist_to_sanlist;
+use shakmaty::{Color, EnPassantMode, Move, Position};
+use ucui_engine::{EngineError, Score};
+use ucui_utils::parse_pgn;
 
+use crate::analysis::{analysis_text, candidate_arrows, pv_text, score_text};
 use crate::board::{render_board, square_at};
-use crate::config::get_engine_color;
-use crate::game::GameState;
+use crate::clock::render_clock;
+use crate::config::{
+    EngineSetup, get_engine, get_engine_by_name, get_engine_color, get_engine_match, get_fen,
+    get_move_delay, get_pgn, get_piece_sets, get_save_pgn, get_strength, get_themes,
+};
+use crate::engine_match::EngineMatch;
+use crate::eval_bar::render_eval_bar;
+use crate::export::write_svg;
+use crate::game::{GameState, Players};
 use crate::gesture::{Gesture, StateStart};
+use crate::launcher::{EngineLauncher, LauncherAction};
+use crate::piece_set::{PieceSet, builtin_names};
 use crate::promotion::render_promotion;
 use crate::proxy::{Proxy, start_engine};
+use crate::settings::{EngineSettings, SettingsAction};
+use crate::setup::SetupState;
 use crate::sources::Sources;
+use crate::strength::{self, StrengthAction, find_preset, preset_label};
+use crate::svg::DEFAULT_SIZE;
+use crate::theme::{self, Theme};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum BoardMode {
     Play,
     Setup,
+    Review,
+    Analysis,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -35,10 +52,37 @@ enum PointerMode {
 pub struct DiagramApp<'a> {
     gesture: Rc<RefCell<Gesture>>,
     game: Arc<RwLock<GameState>>,
-    engine: Arc<Proxy>,
+    /// without one, two players share the board
+    engine: Option<Arc<Proxy>>,
+    /// two engines playing each other, instead of `engine`
+    engine_match: Option<EngineMatch>,
+    /// picking an engine to start
+    launcher: Option<EngineLauncher>,
+    /// an engine that died, to start again
+    dead_engine: Option<EngineSetup>,
+    /// the options of the engine, as edited while it runs
+    settings: Option<EngineSettings>,
+    show_settings: bool,
+    /// the preset the engine plays at, full strength without one
+    strength: Option<&'static str>,
+    show_strength: bool,
     sources: Sources<'a>,
     board_mode: BoardMode,
     pointer_mode: PointerMode,
+    message: Option<String>,
+    next_pgn_game: usize,
+    setup: Option<SetupState>,
+    /// color at the bottom of the board
+    orientation: Color,
+    /// position the engine is analysing
+    analysed: Option<String>,
+    /// sets asked for on the command line, then the built-in ones
+    piece_sets: Vec<String>,
+    piece_set: usize,
+    /// themes asked for on the command line, then the built-in ones
+    themes: Vec<String>,
+    theme_index: usize,
+    theme: Theme,
 }
 
 impl<'a> DiagramApp<'a> {
@@ -58,42 +102,595 @@ impl<'a> DiagramApp<'a> {
         install_image_loaders(&cc.egui_ctx);
         let ctx = Arc::new(Mutex::new(cc.egui_ctx.clone()));
 
-        let game_state = Arc::new(RwLock::new(GameState::new(get_engine_color(), None)));
-        DiagramApp {
+        let (game_state, message) = match GameState::new(get_engine_color(), get_fen()) {
+            Ok(game_state) => (game_state, None),
+            Err(err) => (
+                GameState::new(get_engine_color(), None).expect("initial position is fine"),
+                Some(format!("--fen: {err}")),
+            ),
+        };
+        let mut piece_sets = get_piece_sets();
+        for name in builtin_names() {
+            if !piece_sets.iter().any(|piece_set| piece_set == name) {
+                piece_sets.push(name.to_string());
+            }
+        }
+        let (piece_set, message) = match PieceSet::load(&piece_sets[0]) {
+            Ok(piece_set) => (piece_set, message),
+            Err(err) => (
+                PieceSet::builtin(),
+                message.or(Some(format!("--piece-set: {err}"))),
+            ),
+        };
+        let mut themes = get_themes();
+        for name in theme::builtin_names() {
+            if !themes.iter().any(|theme| theme == name) {
+                themes.push(name.to_string());
+            }
+        }
+        let (theme, message) = match Theme::load(&themes[0]) {
+            Ok(theme) => (theme, message),
+            Err(err) => (
+                Theme::classic(),
+                message.or(Some(format!("--theme: {err}"))),
+            ),
+        };
+
+        let engine_match = get_engine_match();
+        // we play from the bottom of the board, white is there for a match
+        let orientation = if engine_match.is_some() {
+            Color::White
+        } else {
+            !game_state.engine_color
+        };
+        let game_state = Arc::new(RwLock::new(game_state));
+        let (engine, engine_match) = match engine_match {
+            Some((white, black)) => (
+                None,
+                Some(EngineMatch::start(
+                    white,
+                    black,
+                    get_move_delay(),
+                    game_state.clone(),
+                    ctx,
+                )),
+            ),
+            None => (
+                get_engine().map(|setup| Arc::new(start_engine(setup, game_state.clone(), ctx))),
+                None,
+            ),
+        };
+        let mut app = DiagramApp {
             gesture: Rc::new(RefCell::new(Gesture::new())),
             game: game_state.clone(),
-            sources: Sources::new(),
-            engine: Arc::new(start_engine(game_state.clone(), ctx)),
+            sources: Sources::new(&piece_set, &theme),
+            engine,
+            engine_match,
+            launcher: None,
+            dead_engine: None,
+            settings: None,
+            show_settings: false,
+            strength: get_strength(),
+            show_strength: false,
             board_mode: BoardMode::Play,
             pointer_mode: PointerMode::Drag,
+            message,
+            next_pgn_game: 0,
+            setup: None,
+            orientation,
+            analysed: None,
+            piece_sets,
+            piece_set: 0,
+            themes,
+            theme_index: 0,
+            theme,
+        };
+        app.apply_strength();
+        if get_pgn().is_some() {
+            app.load_pgn();
         }
+        app
     }
 }
 
 impl<'a> DiagramApp<'a> {
     fn new_game(&mut self) {
         if let Ok(mut game_state) = self.game.write() {
-            game_state.moves = Vec::new();
-            game_state.game = Chess::new();
-            self.engine.new_game();
+            game_state.reset();
+            if let Some(engine) = &self.engine {
+                engine.new_game();
+            }
+            if let Some(engine_match) = self.engine_match.as_mut() {
+                engine_match.new_game();
+            }
+        }
+    }
+
+    /// Whether we play against an engine and it has the move.
+    fn engine_to_move(&self, game_state: &GameState) -> bool {
+        self.engine.is_some()
+            && self.board_mode == BoardMode::Play
+            && game_state.game.turn() == game_state.engine_color
+    }
+
+    /// Moving through the game, a move being searched isn't wanted anymore.
+    ///
+    /// Called with the game state locked, for the reply not to land on the
+    /// position we move to.
+    fn cancel_search(&self) {
+        if self.board_mode != BoardMode::Play {
+            return;
+        }
+        if let Some(engine) = &self.engine {
+            engine.stop();
+        }
+        if let Some(engine_match) = &self.engine_match {
+            engine_match.stop();
+        }
+    }
+
+    /// Take back our last move, and the engine's reply if it got one.
+    fn undo(&mut self) {
+        if let Ok(mut game_state) = self.game.write() {
+            self.cancel_search();
+            if game_state.undo().is_some() && self.engine_to_move(&game_state) {
+                let _ = game_state.undo();
+            }
+            self.play_engine_turn(&game_state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Ok(mut game_state) = self.game.write() {
+            self.cancel_search();
+            if game_state.redo().is_some() && self.engine_to_move(&game_state) {
+                let _ = game_state.redo();
+            }
+            self.play_engine_turn(&game_state);
+        }
+    }
+
+    /// When we end up on a position where the engine is to move, let it play.
+    fn play_engine_turn(&self, game_state: &GameState) {
+        if self.engine_to_move(game_state)
+            && game_state.outcome().is_none()
+            && let Some(engine) = &self.engine
+        {
+            engine.play(game_state);
+        }
+    }
+
+    /// Start an engine, it joins the game on the board.
+    fn start_engine(&mut self, ctx: &egui::Context, setup: EngineSetup) {
+        self.message = Some(format!("Engine: {}", setup.path));
+        let engine = start_engine(setup, self.game.clone(), Arc::new(Mutex::new(ctx.clone())));
+        engine.new_game();
+        self.engine = Some(Arc::new(engine));
+        self.apply_strength();
+        self.launcher = None;
+        self.dead_engine = None;
+        self.settings = None;
+        self.show_settings = false;
+        if let Ok(game_state) = self.game.read() {
+            self.play_engine_turn(&game_state);
+        }
+    }
+
+    /// Tell what went wrong, and let the engine go if it's gone.
+    fn engine_failed(&mut self, err: EngineError) {
+        log::error!("Engine: {err}");
+        if !err.is_fatal() {
+            self.message = Some(format!("Engine: {err}"));
+            return;
+        }
+        if self.board_mode == BoardMode::Analysis {
+            self.stop_analysis();
+            self.board_mode = BoardMode::Play;
+        }
+        let engine = self.engine.take();
+        self.settings = None;
+        self.show_settings = false;
+        if let EngineError::Died = err {
+            self.dead_engine = engine.map(|engine| engine.setup().clone());
+            self.message = Some(format!("Engine: {err}, restart it with [E]"));
+        } else {
+            self.message = Some(format!("Engine: {err}, start another with [E]"));
+        }
+    }
+
+    /// Stop the match when an engine can't go on.
+    fn match_failed(&mut self, color: Color, err: EngineError) {
+        log::error!("{color} engine: {err}");
+        if err.is_fatal() {
+            self.engine_match = None;
+            self.message = Some(format!("{color} engine: {err}, the match is over"));
+        } else {
+            self.message = Some(format!("{color} engine: {err}"));
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        if let Some(engine_match) = self.engine_match.as_mut() {
+            engine_match.paused = !engine_match.paused;
+            self.message = Some(String::from(if engine_match.paused {
+                "Match paused"
+            } else {
+                "Match resumed"
+            }));
+        }
+    }
+
+    fn apply_strength(&self) {
+        if let Some(engine) = &self.engine {
+            engine.set_strength(
+                self.strength
+                    .and_then(find_preset)
+                    .map(|preset| preset.strength.clone()),
+            );
+        }
+    }
+
+    /// Show or hide the strength presets, for the engine we play against.
+    fn toggle_strength(&mut self) {
+        if self.engine_match.is_some() {
+            self.message = Some(String::from("Engines play at full strength in a match"));
+            return;
+        }
+        self.show_strength = !self.show_strength;
+    }
+
+    fn set_strength(&mut self, name: Option<&'static str>) {
+        self.strength = name;
+        self.apply_strength();
+        self.message = Some(match name.and_then(find_preset) {
+            Some(preset) => format!("Strength: {}", preset_label(preset)),
+            None => String::from("Strength: full"),
+        });
+    }
+
+    /// Show or hide the options the engine declares.
+    fn toggle_settings(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        if self.show_settings {
+            self.show_settings = false;
+            return;
+        }
+        // options are known once the engine answered
+        if self
+            .settings
+            .as_ref()
+            .is_none_or(|settings| settings.is_empty())
+        {
+            self.settings = Some(EngineSettings::new(
+                engine.options(),
+                &engine.setup().options,
+            ));
+        }
+        self.show_settings = true;
+    }
+
+    fn write_pgn(&self, path: &str) -> std::io::Result<()> {
+        let players = match (&self.engine_match, &self.engine) {
+            (Some(engine_match), _) => engine_match.players(),
+            (None, Some(engine)) => Players::Engine(match self.strength {
+                Some(strength) => format!("{} ({strength})", engine.name()),
+                None => engine.name(),
+            }),
+            (None, None) => Players::Humans,
+        };
+        let pgn = self
+            .game
+            .read()
+            .map(|game_state| game_state.pgn(players).to_string())
+            .unwrap_or_default();
+        std::fs::write(path, pgn)
+    }
+
+    /// Write what's on the board as an SVG diagram
+    fn save_diagram(&mut self) {
+        let path = chrono::Local::now()
+            .format("chess-diagram-%Y%m%d-%H%M%S.svg")
+            .to_string();
+        let position = match &self.setup {
+            Some(setup) => setup.setup.clone(),
+            None => match self.game.read() {
+                Ok(game_state) => game_state.game.clone().into_setup(EnPassantMode::Legal),
+                Err(_) => return,
+            },
+        };
+        self.message = Some(
+            match write_svg(
+                &path,
+                &position,
+                &self.theme,
+                None,
+                self.orientation,
+                DEFAULT_SIZE,
+            ) {
+                Ok(_) => format!("Diagram saved to {path}"),
+                Err(err) => format!("Failed to save {path}: {err}"),
+            },
+        );
+    }
+
+    /// Switch to the next piece set, skipping to it even if it fails to load
+    fn next_piece_set(&mut self, ctx: &egui::Context) {
+        self.piece_set = (self.piece_set + 1) % self.piece_sets.len();
+        match PieceSet::load(&self.piece_sets[self.piece_set]) {
+            Ok(piece_set) => {
+                self.sources.set_pieces(ctx, &piece_set);
+                self.message = Some(format!("Pieces: {}", piece_set.name));
+            }
+            Err(err) => {
+                log::error!("Failed to load pieces: {err}");
+                self.message = Some(err);
+            }
+        }
+    }
+
+    /// Switch to the next theme, skipping to it even if it fails to load
+    fn next_theme(&mut self, ctx: &egui::Context) {
+        self.theme_index = (self.theme_index + 1) % self.themes.len();
+        match Theme::load(&self.themes[self.theme_index]) {
+            Ok(theme) => {
+                self.sources.set_theme(ctx, &theme);
+                self.message = Some(format!("Theme: {}", theme.name));
+                self.theme = theme;
+            }
+            Err(err) => {
+                log::error!("Failed to load theme: {err}");
+                self.message = Some(err);
+            }
+        }
+    }
+
+    fn save_pgn(&mut self) {
+        let path = get_save_pgn().unwrap_or_else(|| {
+            chrono::Local::now()
+                .format("chess-diagram-%Y%m%d-%H%M%S.pgn")
+                .to_string()
+        });
+        self.message = Some(match self.write_pgn(&path) {
+            Ok(_) => format!("Game saved to {path}"),
+            Err(err) => format!("Failed to save {path}: {err}"),
+        });
+    }
+
+    /// Load the next game from the PGN file for review
+    fn load_pgn(&mut self) {
+        let Some(path) = get_pgn() else {
+            self.message = Some(String::from("No PGN file given (--pgn)"));
+            return;
+        };
+        let games = match std::fs::read_to_string(&path) {
+            Err(err) => {
+                self.message = Some(format!("Failed to read {path}: {err}"));
+                return;
+            }
+            Ok(content) => match parse_pgn(&content) {
+                Err(err) => {
+                    log::error!("Failed to parse {path}: {err}");
+                    self.message = Some(format!("Failed to parse {path}: {err}"));
+                    return;
+                }
+                Ok(games) => games,
+            },
+        };
+        if games.is_empty() {
+            self.message = Some(format!("No game found in {path}"));
+            return;
+        }
+
+        let index = self.next_pgn_game % games.len();
+        let pgn = &games[index];
+        if let Ok(mut game_state) = self.game.write() {
+            game_state.load(pgn);
+            if let Some(engine) = &self.engine {
+                engine.new_game();
+            }
+            if let Some(engine_match) = self.engine_match.as_mut() {
+                engine_match.new_game();
+            }
+        }
+        self.set_board_mode(BoardMode::Review);
+        self.next_pgn_game = index + 1;
+        self.message = Some(format!(
+            "{}/{} {} - {} ({})",
+            index + 1,
+            games.len(),
+            pgn.tag("White").unwrap_or("?"),
+            pgn.tag("Black").unwrap_or("?"),
+            pgn.tag("Event").unwrap_or("?"),
+        ));
+    }
+
+    /// Stepping through a game is for reviewing or analysing it, an
+    /// engine we play against would be left without a search.
+    fn can_step(&self) -> bool {
+        matches!(self.board_mode, BoardMode::Review | BoardMode::Analysis)
+    }
+
+    fn step_back(&mut self) {
+        if self.can_step()
+            && let Ok(mut game_state) = self.game.write()
+        {
+            let _ = game_state.undo();
+        }
+    }
+
+    fn step_forward(&mut self) {
+        if self.can_step()
+            && let Ok(mut game_state) = self.game.write()
+        {
+            let _ = game_state.redo();
+        }
+    }
+
+    fn step_first(&mut self) {
+        if self.can_step()
+            && let Ok(mut game_state) = self.game.write()
+        {
+            while game_state.undo().is_some() {}
+        }
+    }
+
+    fn step_last(&mut self) {
+        if self.can_step()
+            && let Ok(mut game_state) = self.game.write()
+        {
+            while game_state.redo().is_some() {}
+        }
+    }
+
+    fn copy_fen(&mut self, ctx: &egui::Context) {
+        let fen = match &self.setup {
+            Some(setup) => setup.fen(),
+            None => self
+                .game
+                .read()
+                .map(|game_state| game_state.fen())
+                .unwrap_or_default(),
+        };
+        ctx.copy_text(fen.clone());
+        self.message = Some(format!("Copied {fen}"));
+    }
+
+    /// A pasted FEN goes to the setup if we're in it, otherwise it starts a new game.
+    fn paste_fen(&mut self, text: &str) {
+        let fen_string = text.trim();
+        if let Some(setup) = self.setup.as_mut() {
+            match Fen::from_str(fen_string) {
+                Ok(fen) => {
+                    setup.setup = fen.into_setup();
+                    setup.error = None;
+                    self.message = None;
+                }
+                Err(err) => self.message = Some(format!("Invalid FEN: {err}")),
+            }
+        } else if let Ok(mut game_state) = self.game.write() {
+            match game_state.set_position(fen_string) {
+                Ok(_) => {
+                    if let Some(engine) = &self.engine {
+                        engine.new_game();
+                    }
+                    if let Some(engine_match) = self.engine_match.as_mut() {
+                        engine_match.new_game();
+                    }
+                    self.message = None;
+                    self.play_engine_turn(&game_state);
+                }
+                Err(err) => self.message = Some(err),
+            }
+        }
+    }
+
+    /// The position being set up becomes the starting position of a new game,
+    /// if it's a legal one.
+    fn leave_setup(&mut self) -> bool {
+        let Some(setup) = self.setup.as_mut() else {
+            return true;
+        };
+        let Ok(mut game_state) = self.game.write() else {
+            return false;
+        };
+        match game_state.set_position(&setup.fen()) {
+            Ok(_) => {
+                if let Some(engine) = &self.engine {
+                    engine.new_game();
+                }
+                if let Some(engine_match) = self.engine_match.as_mut() {
+                    engine_match.new_game();
+                }
+                self.setup = None;
+                self.message = None;
+                true
+            }
+            Err(err) => {
+                setup.error = Some(err.clone());
+                self.message = Some(err);
+                false
+            }
         }
     }
 
     fn set_board_mode(&mut self, mode: BoardMode) {
         use BoardMode::*;
+        if mode != Setup && !self.leave_setup() {
+            return;
+        }
+        if self.board_mode == Analysis && mode != Analysis {
+            self.stop_analysis();
+        }
         match mode {
-            Setup => self.board_mode = Setup,
+            Setup => {
+                if self.board_mode != Setup
+                    && let Ok(game_state) = self.game.read()
+                {
+                    self.setup = Some(SetupState::from_position(&game_state.game));
+                    let _ = self.gesture.try_borrow_mut().map(|mut gesture| {
+                        *gesture = Gesture::None;
+                    });
+                }
+                self.board_mode = Setup;
+            }
+            Review => self.board_mode = Review,
+            Analysis => {
+                if self.engine.is_none() {
+                    self.message = Some(String::from(if self.engine_match.is_some() {
+                        "No engine to analyse during a match"
+                    } else {
+                        "No engine, start one with [E]"
+                    }));
+                    return;
+                }
+                self.board_mode = Analysis;
+            }
             Play => {
                 self.board_mode = Play;
-                if let Ok(game_state) = self.game.read() {
-                    self.engine.play(
-                        Fen::from_position(game_state.game.clone(), shakmaty::EnPassantMode::Legal)
-                            .to_string(),
-                    );
+                if let Some(engine) = &self.engine
+                    && let Ok(game_state) = self.game.read()
+                {
+                    engine.play(&game_state);
                 }
             }
         }
     }
+    /// Keep the engine on the position on the board, restarting
+    /// the search each time it changes.
+    fn follow_analysis(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        if let Ok(mut game_state) = self.game.write() {
+            let fen = game_state.fen();
+            if self.analysed.as_ref() != Some(&fen) {
+                game_state.analysis.clear();
+                if game_state.game.is_game_over() {
+                    engine.stop();
+                } else {
+                    engine.analyse(fen.clone());
+                }
+                self.analysed = Some(fen);
+            }
+        }
+    }
+
+    fn stop_analysis(&mut self) {
+        if let Some(engine) = &self.engine {
+            engine.stop();
+        }
+        self.analysed = None;
+        if let Ok(mut game_state) = self.game.write() {
+            game_state.analysis.clear();
+        }
+    }
+
+    fn flip_board(&mut self) {
+        self.orientation = !self.orientation;
+    }
+
     fn toggle_pointer_mode(&mut self) {
         use PointerMode::*;
         let _ = self.gesture.try_borrow_mut().map(|mut gesture| {
@@ -107,6 +704,15 @@ impl<'a> DiagramApp<'a> {
 }
 
 impl<'a> eframe::App for DiagramApp<'a> {
+    /// Called once on shutdown, writes the game if asked to.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Some(path) = get_save_pgn()
+            && let Err(err) = self.write_pgn(&path)
+        {
+            log::error!("Failed to save {path}: {err}");
+        }
+    }
+
     /// Called by the framework to save state before shutdown.
 
     /// Called each time the UI needs repainting, which may be many times per second.
@@ -114,6 +720,35 @@ impl<'a> eframe::App for DiagramApp<'a> {
         // let gesture = &mut self.gesture;
         let gesture = self.gesture.clone();
         let game_state = self.game.clone();
+        if let Ok(mut game_state) = self.game.write()
+            && let Some(clock) = game_state.clock.as_mut()
+        {
+            if clock.flagged().is_some() {
+                clock.stop();
+            } else if clock.is_running() {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+        if let Some(err) = self.engine.as_ref().and_then(|engine| engine.take_error()) {
+            self.engine_failed(err);
+        }
+        if let Some((color, err)) = self
+            .engine_match
+            .as_ref()
+            .and_then(|engine_match| engine_match.take_error())
+        {
+            self.match_failed(color, err);
+        }
+        if self.board_mode == BoardMode::Play
+            && let Some(engine_match) = self.engine_match.as_mut()
+            && let Ok(mut game_state) = self.game.write()
+            && let Some(wait) = engine_match.step(&mut game_state)
+        {
+            ctx.request_repaint_after(wait);
+        }
+        if self.board_mode == BoardMode::Analysis {
+            self.follow_analysis();
+        }
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             let toggle_pointer = format!(
                 "Toggle {}",
@@ -123,22 +758,120 @@ impl<'a> eframe::App for DiagramApp<'a> {
                     "click"
                 }
             );
+            let engine_keys: &[(&str, &str)] = if self.engine_match.is_some() {
+                &[("P", "Match"), ("Space", "Pause")]
+            } else if self.engine.is_some() {
+                &[
+                    ("P", "Engine Play"),
+                    ("A", "Analysis"),
+                    ("O", "Engine options"),
+                    ("K", "Strength"),
+                ]
+            } else if self.dead_engine.is_some() {
+                &[("P", "Play"), ("E", "Restart engine")]
+            } else {
+                &[("P", "Play"), ("E", "Start engine")]
+            };
             ui.horizontal(|ui| {
-                let keys = [
+                let mut keys = vec![
                     ("Q", "Quit"),
                     ("F", "Fullscreen"),
                     ("N", "New game"),
                     ("S", "Setup"),
-                    ("P", "Engine Play"),
-                    ("I", toggle_pointer.as_str()),
                 ];
+                keys.extend_from_slice(engine_keys);
+                keys.extend([
+                    ("I", toggle_pointer.as_str()),
+                    ("U", "Undo"),
+                    ("R", "Redo"),
+                    ("W", "Save PGN"),
+                    ("L", "Load PGN"),
+                    ("←→", "Step"),
+                    ("Ctrl+C", "Copy FEN"),
+                    ("Ctrl+V", "Paste FEN"),
+                    ("X", "Flip board"),
+                    ("D", "Save diagram"),
+                    ("G", "Pieces"),
+                    ("T", "Theme"),
+                ]);
                 for (key, label) in keys {
                     ui.label(format!("[{key}] {label}"));
                     ui.separator();
                 }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
             });
         });
 
+        if let Some(launcher) = self.launcher.as_mut() {
+            let mut action = None;
+            egui::SidePanel::right("engine_panel")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    action = launcher.render_panel(ui);
+                });
+            match action {
+                Some(LauncherAction::Start(name)) => {
+                    self.start_engine(ctx, get_engine_by_name(&name))
+                }
+                Some(LauncherAction::Cancel) => self.launcher = None,
+                None => {}
+            }
+        }
+
+        if self.show_settings
+            && let Some(settings) = self.settings.as_mut()
+        {
+            let mut action = None;
+            egui::SidePanel::right("engine_settings")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    action = settings.render_panel(ui);
+                });
+            match action {
+                Some(SettingsAction::Set(name, value)) => {
+                    if let Some(engine) = &self.engine {
+                        engine.set_option(name, value);
+                    }
+                }
+                Some(SettingsAction::Close) => self.show_settings = false,
+                None => {}
+            }
+        }
+
+        if self.show_strength {
+            let mut action = None;
+            egui::SidePanel::right("strength_panel")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    action = strength::render_panel(ui, self.strength);
+                });
+            match action {
+                Some(StrengthAction::Pick(name)) => {
+                    self.set_strength(name);
+                    self.show_strength = false;
+                }
+                Some(StrengthAction::Close) => self.show_strength = false,
+                None => {}
+            }
+        }
+
+        let mut setup_done = false;
+        if let Some(setup) = self.setup.as_mut() {
+            egui::SidePanel::left("setup_panel")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    setup_done = setup.render_panel(ui, &self.sources);
+                });
+        }
+        if setup_done && self.leave_setup() {
+            self.board_mode = BoardMode::Play;
+            if let Ok(game_state) = self.game.read() {
+                self.play_engine_turn(&game_state);
+            }
+        }
+
         // {
         //     egui::SidePanel::right("side")
         //         .resizable(false)
@@ -159,40 +892,31 @@ impl<'a> eframe::App for DiagramApp<'a> {
                 {
                     let gesture = gesture.borrow();
                     let game_state = game_state.read().unwrap();
-                    let title = if let Some(outcome) = game_state.game.outcome() {
-                        Some(outcome.to_string())
+                    let title = if let Some(outcome) = game_state.outcome() {
+                        match game_state.flagged() {
+                            Some(color) if game_state.game.outcome().is_none() => {
+                                Some(format!("{outcome}  ({color} out of time)"))
+                            }
+                            _ => Some(outcome.to_string()),
+                        }
+                    } else if self.board_mode == BoardMode::Analysis {
+                        game_state
+                            .analysis
+                            .first()
+                            .map(|info| analysis_text(&game_state.game, info))
                     } else if let Score::Mate { moves } = game_state.score {
                         Some(format!("Mate in {moves}"))
-                    } else if let Score::CentiPawns { score, pv } = &game_state.score {
-                        let mut game = Chess::new();
-                        let n = game_state.moves.len() - 1;
+                    } else if let Score::CentiPawns { pv, .. } = &game_state.score {
+                        // the engine's line starts before its last move
+                        let mut game = game_state.start.clone();
+                        let n = game_state.moves.len().saturating_sub(1);
                         for m in game_state.moves.iter().take(n) {
                             let _ = game.clone().play(m).map(|new_game| {
                                 game = new_game;
                             });
                         }
-                        let start = game.fullmoves();
-                        let sanlist = if game.turn() == Color::Black {
-                            let mut sanlist = vec![String::from("…")];
-                            sanlist.extend(ucimovelist_to_sanlist(game, pv));
-                            sanlist
-                        } else {
-                            ucimovelist_to_sanlist(game, pv)
-                        };
-                        let moves: Vec<String> = sanlist
-                            .chunks(2)
-                            .enumerate()
-                            .map(|(i, pair)| match (pair.get(0), pair.get(1)) {
-                                (Some(a), Some(b)) => {
-                                    format!("{}.{} {}", start.saturating_add(i as u32), a, b)
-                                }
-                                (Some(a), None) => {
-                                    format!("{}.{} …", i + 1, a)
-                                }
-                                _ => String::from("??"),
-                            })
-                            .collect();
-                        Some(format!("[{}]  {}", *score as f32 / 100.0, moves.join("  ")))
+                        let score = score_text(&game_state.score, game.turn()).unwrap_or_default();
+                        Some(format!("[{score}]  {}", pv_text(game, pv)))
                     } else {
                         game_state.opening.clone().and_then(|eco| {
                             if eco.moves.len() >= game_state.moves.len() {
@@ -212,21 +936,61 @@ impl<'a> eframe::App for DiagramApp<'a> {
                         None
                     };
 
-                    render_board(
-                        ctx,
-                        ui,
-                        &self.sources,
-                        &gesture,
-                        &game_state.game,
-                        game_state.moves.last(),
-                        title,
-                        highlight_square,
-                    );
+                    if let Some(setup) = &self.setup {
+                        render_board(
+                            ctx,
+                            ui,
+                            &self.sources,
+                            &self.theme,
+                            &gesture,
+                            &setup.setup,
+                            None,
+                            Some(setup.fen()),
+                            None,
+                            &[],
+                            self.orientation,
+                        );
+                        setup.render_dragged(ctx, ui, &self.sources);
+                    } else {
+                        render_board(
+                            ctx,
+                            ui,
+                            &self.sources,
+                            &self.theme,
+                            &gesture,
+                            &game_state.game.clone().into_setup(EnPassantMode::Legal),
+                            game_state.moves.last(),
+                            title,
+                            highlight_square,
+                            &candidate_arrows(&game_state.game, &game_state.analysis),
+                            self.orientation,
+                        );
+                        if let Some(clock) = &game_state.clock {
+                            render_clock(ctx, ui, clock, self.orientation);
+                        }
+                        // the analysis is for the side to move, a score for the side that just moved
+                        match (self.board_mode, game_state.analysis.first()) {
+                            (BoardMode::Analysis, Some(line)) => render_eval_bar(
+                                ctx,
+                                ui,
+                                &line.score,
+                                game_state.game.turn(),
+                                self.orientation,
+                            ),
+                            _ => render_eval_bar(
+                                ctx,
+                                ui,
+                                &game_state.score,
+                                !game_state.game.turn(),
+                                self.orientation,
+                            ),
+                        }
+                    }
                 }
                 {
                     let mut gesture = gesture.borrow_mut();
                     if gesture.need_promotion() {
-                        render_promotion(ctx, ui, &self.sources, &mut gesture);
+                        render_promotion(ctx, ui, &self.sources, &mut gesture, self.orientation);
                         return;
                     }
                 }
@@ -271,26 +1035,26 @@ impl<'a> eframe::App for DiagramApp<'a> {
                         *gesture = Gesture::new();
                         game_state.clear_score();
                         game_state.make_move(move_);
-                        if self.board_mode == BoardMode::Play {
+                        if self.board_mode == BoardMode::Play && self.engine.is_some() {
                             if let Some((move_, _)) =
                                 game_state.openings.find_move(&game_state.game)
                             {
                                 game_state.make_move(move_);
-                            } else {
-                                self.engine.play(
-                                    Fen::from_position(
-                                        game_state.game.clone(),
-                                        shakmaty::EnPassantMode::Legal,
-                                    )
-                                    .to_string(),
-                                );
+                            } else if game_state.outcome().is_none()
+                                && let Some(engine) = &self.engine
+                            {
+                                engine.play(&game_state);
                             }
                         }
                     }
                     _ => {}
                 }
 
-                if self.pointer_mode == PointerMode::Click {
+                if let Some(setup) = self.setup.as_mut() {
+                    setup.handle_input(ui, self.orientation);
+                } else if self.engine_match.is_some() && self.board_mode == BoardMode::Play {
+                    // the match plays itself, the board doesn't take moves
+                } else if self.pointer_mode == PointerMode::Click {
                     ui.input(|input| {
                         if let Some(position) = input.pointer.interact_pos()
                             && input.pointer.primary_clicked()
@@ -299,7 +1063,9 @@ impl<'a> eframe::App for DiagramApp<'a> {
                                 log::info!("CLICk {:?}", gesture);
                                 match *gesture {
                                     Gesture::None => {
-                                        if let Some(from) = square_at(&ui.max_rect(), position) {
+                                        if let Some(from) =
+                                            square_at(&ui.max_rect(), position, self.orientation)
+                                        {
                                             let _ = game_state.read().map(|game_state| {
                                                 game_state.game.board().piece_at(from).map(
                                                     |piece| {
@@ -314,14 +1080,16 @@ impl<'a> eframe::App for DiagramApp<'a> {
                                         }
                                     }
                                     Gesture::Start(StateStart { from, .. }) => {
-                                        square_at(&ui.max_rect(), position).map(|to| {
-                                            info!("ON  {to}",);
-                                            if from != to {
-                                                *gesture = gesture.moving(position).end(to);
-                                            } else {
-                                                *gesture = Gesture::None;
-                                            }
-                                        });
+                                        square_at(&ui.max_rect(), position, self.orientation).map(
+                                            |to| {
+                                                info!("ON  {to}",);
+                                                if from != to {
+                                                    *gesture = gesture.moving(position).end(to);
+                                                } else {
+                                                    *gesture = Gesture::None;
+                                                }
+                                            },
+                                        );
                                     }
                                     _ => {}
                                 }
@@ -335,7 +1103,8 @@ impl<'a> eframe::App for DiagramApp<'a> {
                                 let _ = gesture.try_borrow_mut().map(|mut gesture| {
                                     log::info!("PRESS {:?}", gesture);
                                     if let Gesture::None = *gesture
-                                        && let Some(from) = square_at(&ui.max_rect(), position)
+                                        && let Some(from) =
+                                            square_at(&ui.max_rect(), position, self.orientation)
                                     {
                                         let _ = game_state.read().map(|game_state| {
                                             game_state.game.board().piece_at(from).map(|piece| {
@@ -351,7 +1120,9 @@ impl<'a> eframe::App for DiagramApp<'a> {
                                 });
                             } else if input.pointer.button_released(egui::PointerButton::Primary) {
                                 log::info!("RELEASE");
-                                if let Some(to) = square_at(&ui.max_rect(), position) {
+                                if let Some(to) =
+                                    square_at(&ui.max_rect(), position, self.orientation)
+                                {
                                     let _ = gesture.try_borrow_mut().map(|mut gesture| {
                                         info!("end to {}", &to);
                                         *gesture = gesture.end(to);
@@ -365,8 +1136,26 @@ impl<'a> eframe::App for DiagramApp<'a> {
                 // if processed inside ui.input, it deadlocks on rwlock<context> acquisisition
                 let mut viewport_commands: Vec<egui::ViewportCommand> = Vec::new();
                 let vcr = &mut viewport_commands;
+                let mut copy = false;
+                let mut switch_pieces = false;
+                let mut switch_theme = false;
+                let mut launch_engine = false;
+                let mut pasted: Option<String> = None;
+                // keys go to the text being typed in
+                let typing = ctx.wants_keyboard_input();
 
                 ui.input(|input| {
+                    if typing {
+                        return;
+                    }
+                    for event in input.events.iter() {
+                        match event {
+                            egui::Event::Copy => copy = true,
+                            egui::Event::Paste(text) => pasted = Some(text.clone()),
+                            _ => {}
+                        }
+                    }
+
                     if input.key_released(Key::F) {
                         let current = egui::ViewportInfo::default().fullscreen.unwrap_or(false);
                         // ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!current));
@@ -390,11 +1179,101 @@ impl<'a> eframe::App for DiagramApp<'a> {
                         self.set_board_mode(BoardMode::Play);
                     }
 
+                    if input.key_released(Key::A) {
+                        self.set_board_mode(BoardMode::Analysis);
+                    }
+
+                    if input.key_released(Key::E)
+                        && self.engine.is_none()
+                        && self.engine_match.is_none()
+                    {
+                        launch_engine = true;
+                    }
+
+                    if input.key_released(Key::Space) {
+                        self.toggle_pause();
+                    }
+
+                    if input.key_released(Key::O) {
+                        self.toggle_settings();
+                    }
+
+                    if input.key_released(Key::K) {
+                        self.toggle_strength();
+                    }
+
                     if input.key_released(Key::I) {
                         self.toggle_pointer_mode();
                     }
+
+                    if input.key_released(Key::U) {
+                        self.undo();
+                    }
+
+                    if input.key_released(Key::R) {
+                        self.redo();
+                    }
+
+                    if input.key_released(Key::W) {
+                        self.save_pgn();
+                    }
+
+                    if input.key_released(Key::D) {
+                        self.save_diagram();
+                    }
+
+                    if input.key_released(Key::G) {
+                        switch_pieces = true;
+                    }
+
+                    if input.key_released(Key::T) {
+                        switch_theme = true;
+                    }
+
+                    if input.key_released(Key::X) {
+                        self.flip_board();
+                    }
+
+                    if input.key_released(Key::L) {
+                        self.load_pgn();
+                    }
+
+                    if input.key_pressed(Key::ArrowLeft) {
+                        self.step_back();
+                    }
+
+                    if input.key_pressed(Key::ArrowRight) {
+                        self.step_forward();
+                    }
+
+                    if input.key_pressed(Key::Home) {
+                        self.step_first();
+                    }
+
+                    if input.key_pressed(Key::End) {
+                        self.step_last();
+                    }
                 });
 
+                if copy {
+                    self.copy_fen(ctx);
+                }
+                if let Some(text) = pasted {
+                    self.paste_fen(&text);
+                }
+                if switch_pieces {
+                    self.next_piece_set(ctx);
+                }
+                if switch_theme {
+                    self.next_theme(ctx);
+                }
+                if launch_engine {
+                    match self.dead_engine.take() {
+                        Some(setup) => self.start_engine(ctx, setup),
+                        None => self.launcher = Some(EngineLauncher::new()),
+                    }
+                }
+
                 for command in viewport_commands {
                     ctx.send_viewport_cmd(command);
                 }
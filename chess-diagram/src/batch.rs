@@ -0,0 +1,196 @@
+use std::{fs, path::Path};
+
+use shakmaty::{Chess, Color, EnPassantMode, Move, Position, san::SanPlus};
+use ucui_utils::{MoveAnnotation, PgnGame, parse_pgn};
+
+use crate::{
+    export::{write_png, write_svg},
+    piece_set::PieceSet,
+    theme::Theme,
+};
+
+enum Format {
+    Svg,
+    Png,
+}
+
+/// A diagram to draw from a game.
+struct Diagram {
+    ply: usize,
+    // of the last move
+    move_number: u32,
+    position: Chess,
+    last_move: Option<Move>,
+    title: String,
+}
+
+/// "White - Black, Event, Year", leaving out what's unknown.
+fn game_title(game: &PgnGame) -> String {
+    let known = |name: &str| {
+        game.tag(name)
+            .filter(|value| !value.is_empty() && *value != "?")
+    };
+    let mut parts = vec![format!(
+        "{} - {}",
+        known("White").unwrap_or("?"),
+        known("Black").unwrap_or("?")
+    )];
+    if let Some(event) = known("Event") {
+        parts.push(event.to_string());
+    }
+    if let Some(year) = known("Date")
+        .and_then(|date| date.split('.').next())
+        .filter(|year| !year.contains('?'))
+    {
+        parts.push(year.to_string());
+    }
+    parts.join(", ")
+}
+
+/// The move as "12.Nf3" or "12…Nf6", played from `position`.
+fn move_title(position: &Chess, move_: &Move) -> String {
+    let number = position.fullmoves();
+    let san = SanPlus::from_move(position.clone(), move_);
+    if position.turn() == Color::White {
+        format!("{number}.{san}")
+    } else {
+        format!("{number}…{san}")
+    }
+}
+
+fn is_marked(annotation: &MoveAnnotation, flag: &str) -> bool {
+    !annotation.nags.is_empty()
+        || annotation
+            .comment
+            .as_ref()
+            .is_some_and(|comment| comment.contains(flag))
+}
+
+/// The position after `ply` moves of the game, or its last one.
+fn game_diagram(game: &PgnGame, ply: Option<usize>) -> Diagram {
+    let ply = ply.unwrap_or(game.moves.len()).min(game.moves.len());
+    let mut position = game.start.clone();
+    let mut move_number = position.fullmoves().get();
+    for move_ in &game.moves[..ply] {
+        move_number = position.fullmoves().get();
+        position.play_unchecked(move_);
+    }
+    Diagram {
+        ply,
+        move_number,
+        position,
+        last_move: ply.checked_sub(1).map(|index| game.moves[index].clone()),
+        title: game_title(game),
+    }
+}
+
+/// The positions after each marked move of the game.
+fn marked_diagrams(game: &PgnGame, flag: &str) -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let mut position = game.start.clone();
+    for (index, move_) in game.moves.iter().enumerate() {
+        let title = move_title(&position, move_);
+        let move_number = position.fullmoves().get();
+        position.play_unchecked(move_);
+        let ply = index + 1;
+        if game
+            .annotation(ply)
+            .is_some_and(|annotation| is_marked(annotation, flag))
+        {
+            diagrams.push(Diagram {
+                ply,
+                move_number,
+                position: position.clone(),
+                last_move: Some(move_.clone()),
+                title,
+            });
+        }
+    }
+    diagrams
+}
+
+/// Keep tag values from making paths of their own.
+fn file_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fill the template's placeholders for a diagram of the `number`th game.
+fn file_name(template: &str, game: &PgnGame, number: usize, diagram: &Diagram) -> String {
+    let tag = |name: &str| file_safe(game.tag(name).unwrap_or("unknown"));
+    template
+        .replace("{game}", &number.to_string())
+        .replace("{ply}", &diagram.ply.to_string())
+        .replace("{move}", &diagram.move_number.to_string())
+        .replace("{white}", &tag("White"))
+        .replace("{black}", &tag("Black"))
+        .replace("{event}", &tag("Event"))
+        .replace("{round}", &tag("Round"))
+        .replace("{date}", &tag("Date"))
+}
+
+/// Write diagrams for the games of a PGN file, one per game at `ply`
+/// or one per marked move.
+#[allow(clippy::too_many_arguments)]
+pub fn run_batch(
+    path: &str,
+    marked: Option<&str>,
+    ply: Option<usize>,
+    template: &str,
+    piece_set: &PieceSet,
+    theme: &Theme,
+    orientation: Color,
+    size: u32,
+) -> Result<(), String> {
+    let format = match Path::new(template).extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => Format::Svg,
+        Some("png") => Format::Png,
+        _ => return Err(format!("'{template}' should end with .svg or .png")),
+    };
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("failed to read {path}: {err}"))?;
+    let games = parse_pgn(&content).map_err(|err| format!("invalid PGN in {path}: {err}"))?;
+
+    for (index, game) in games.iter().enumerate() {
+        let diagrams = match marked {
+            Some(flag) => marked_diagrams(game, flag),
+            None => vec![game_diagram(game, ply)],
+        };
+        for diagram in diagrams {
+            let output = file_name(template, game, index + 1, &diagram);
+            if let Some(parent) = Path::new(&output).parent()
+                && !parent.as_os_str().is_empty()
+            {
+                fs::create_dir_all(parent)
+                    .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+            }
+            let setup = diagram.position.into_setup(EnPassantMode::Legal);
+            let title = Some(diagram.title.as_str());
+            match format {
+                Format::Svg => write_svg(&output, &setup, theme, title, orientation, size as f32)
+                    .map_err(|err| format!("failed to write {output}: {err}"))?,
+                Format::Png => write_png(
+                    &output,
+                    &setup,
+                    piece_set,
+                    theme,
+                    diagram.last_move.as_ref(),
+                    title,
+                    orientation,
+                    size,
+                )
+                .map_err(|err| format!("failed to write {output}: {err}"))?,
+            }
+            println!("{output}");
+        }
+    }
+    Ok(())
+}
@@ -4,14 +4,20 @@ use egui::{
     Align2, Color32, Context, CornerRadius, FontId, Pos2, Rect, Stroke, StrokeKind, Ui, Vec2, pos2,
     vec2,
 };
-use shakmaty::{Chess, File, Move, Position, Rank, Square};
+use shakmaty::{Color, File, Move, Rank, Setup, Square};
 
-use crate::{gesture::Gesture, sources::Sources};
+use crate::{
+    gesture::Gesture,
+    sources::Sources,
+    theme::{Placement, SquareFill, Theme},
+};
 
 const LIGHT_SQUARE: bool = true;
 const DARK_SQUARE: bool = false;
 
-const MARGIN: f32 = 64.0;
+pub const MARGIN: f32 = 64.0;
+/// between the board and its frame
+pub const FRAME_GAP: f32 = 3.0;
 
 #[rustfmt::skip]
 const BOARD_COLORS: [[bool;8];8] = [
@@ -25,15 +31,90 @@ const BOARD_COLORS: [[bool;8];8] = [
     [DARK_SQUARE,LIGHT_SQUARE, DARK_SQUARE,LIGHT_SQUARE, DARK_SQUARE,LIGHT_SQUARE, DARK_SQUARE, LIGHT_SQUARE],
 ];
 
+/// A move drawn on the board, `weight` in 0..=1 sets how thick and opaque it is
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    pub weight: f32,
+}
+
+/// A coordinate, anchored as `Painter::text` does it.
+pub struct Label {
+    pub text: String,
+    pub pos: Pos2,
+    pub align: Align2,
+}
+
+/// Ranks and files of the board as the theme places them. Outside,
+/// the destination of the last move is marked along them.
+pub fn coordinate_labels(
+    board_rect: &Rect,
+    orientation: Color,
+    placement: Placement,
+    last_move_to: Option<Square>,
+) -> Vec<Label> {
+    let square_size = board_rect.width() / 8.0;
+    let padding = square_size * 0.05;
+    let mut labels = Vec::new();
+    for index in 0..8usize {
+        let rank = square_from_indices(index, 0, orientation).rank();
+        let file = square_from_indices(7, index, orientation).file();
+        let top = board_rect.min.y + index as f32 * square_size;
+        let left = board_rect.min.x + index as f32 * square_size;
+        match placement {
+            Placement::Outside => {
+                let rank_marked = last_move_to.is_some_and(|to| to.rank() == rank);
+                let file_marked = last_move_to.is_some_and(|to| to.file() == file);
+                labels.push(Label {
+                    text: if rank_marked {
+                        format!("{rank} ·")
+                    } else {
+                        format!("{rank}  ")
+                    },
+                    pos: pos2(board_rect.min.x - (MARGIN / 2.0), top + square_size),
+                    align: Align2::CENTER_BOTTOM,
+                });
+                labels.push(Label {
+                    text: if file_marked {
+                        format!("  {file} ·")
+                    } else {
+                        format!("  {file}  ")
+                    },
+                    pos: pos2(left + square_size / 2.0, board_rect.max.y + (MARGIN * 0.6)),
+                    align: Align2::CENTER_BOTTOM,
+                });
+            }
+            Placement::Inside => {
+                labels.push(Label {
+                    text: rank.to_string(),
+                    pos: pos2(board_rect.min.x + padding, top + padding),
+                    align: Align2::LEFT_TOP,
+                });
+                labels.push(Label {
+                    text: file.to_string(),
+                    pos: pos2(left + square_size - padding, board_rect.max.y - padding),
+                    align: Align2::RIGHT_BOTTOM,
+                });
+            }
+        }
+    }
+    labels
+}
+
+/// Render the board with `orientation` at the bottom.
+#[allow(clippy::too_many_arguments)]
 pub fn render_board(
     ctx: &Context,
     ui: &Ui,
     sources: &Sources<'_>,
+    theme: &Theme,
     gesture: &Gesture,
-    game: &Chess,
+    position: &Setup,
     last_move: Option<&Move>,
     title: Option<String>,
     highlight_square: Option<Square>,
+    arrows: &[Arrow],
+    orientation: Color,
 ) {
     // let mut state = ss_main.borrow_mut();
     let lid = ui.layer_id();
@@ -44,7 +125,7 @@ pub fn render_board(
     let square_size = board_rect.width() / 8.0;
 
     // backround
-    let _ = painter.rect_filled(rect, CornerRadius::ZERO, Color32::WHITE);
+    let _ = painter.rect_filled(rect, CornerRadius::ZERO, theme.background);
 
     // title
     title.map(|title| {
@@ -53,30 +134,34 @@ pub fn render_board(
             rect.min.y + MARGIN / 3.0,
         );
         let align = Align2::CENTER_TOP;
-        let font = FontId::proportional(16.0);
-        let color = Color32::BLACK;
+        let font = FontId::new(16.0, theme.coordinates.font.family());
+        let color = theme.coordinates.color;
         let _ = painter.text(pos, align, title, font, color);
     });
 
     // frame
-    let _ = painter.rect_stroke(
-        board_rect.expand(6.0),
-        CornerRadius::same(1),
-        (6.0, Color32::BLACK),
-        StrokeKind::Middle,
-    );
+    if theme.frame.width > 0.0 {
+        let _ = painter.rect_stroke(
+            board_rect.expand(FRAME_GAP + theme.frame.width / 2.0),
+            CornerRadius::same(1),
+            (theme.frame.width, theme.frame.color),
+            StrokeKind::Middle,
+        );
+    }
 
     // turn
     let turn_margin_x = MARGIN * 0.7;
     let turn_margin_y = MARGIN / 3.0;
     let turn_radius = 12.0;
-    if game.turn() == shakmaty::Color::Black {
-        let top = board_rect.min.y + turn_margin_y;
-        let left = board_rect.max.x + turn_margin_x;
+    let top = if position.turn == orientation {
+        board_rect.max.y - turn_margin_y
+    } else {
+        board_rect.min.y + turn_margin_y
+    };
+    let left = board_rect.max.x + turn_margin_x;
+    if position.turn == Color::Black {
         let _ = painter.circle(pos2(left, top), turn_radius, Color32::BLACK, Stroke::NONE);
     } else {
-        let top = board_rect.max.y - turn_margin_y;
-        let left = board_rect.max.x + turn_margin_x;
         let stroke_width = 3.0;
         // let radius = turn_radius - (stroke_width / 2.0);
         let radius = turn_radius - stroke_width;
@@ -88,8 +173,8 @@ pub fn render_board(
         );
     }
 
-    // let last_move_from = last_move.and_then(|move_| move_.from());
-    let last_move_to = last_move.map(|move_| move_.to());
+    let last_move_from = last_move.and_then(|move_| move_.from());
+    let last_move_to = last_move.map(ucui_utils::move_classic_to);
 
     for rank_index in 0..8usize {
         for file_index in 0..8usize {
@@ -98,77 +183,38 @@ pub fn render_board(
             let color = BOARD_COLORS[rank_index][file_index];
             let square_rect =
                 Rect::from_two_pos(pos2(left, top), pos2(left + square_size, top + square_size));
-            if color {
-                let _ = painter.rect_filled(square_rect, CornerRadius::ZERO, Color32::WHITE);
+            let (fill, source_name) = if color {
+                (&theme.light, "light-square")
             } else {
-                let _ = sources.get("dark-square").map(|dark_square| {
-                    dark_square.paint_at(ui, square_rect);
-                });
+                (&theme.dark, "dark-square")
+            };
+            match fill {
+                SquareFill::Color(fill_color) => {
+                    let _ = painter.rect_filled(square_rect, CornerRadius::ZERO, *fill_color);
+                }
+                SquareFill::Hatching | SquareFill::Texture(_) => {
+                    let _ = sources.get(source_name).map(|texture| {
+                        texture.paint_at(ui, square_rect);
+                    });
+                }
             }
 
-            let rank = rank_from_index(rank_index);
-            let file = file_from_index(file_index);
-            let square = Square::from_coords(file, rank);
-
-            let (text_color, font, rank_text, file_text) = {
-                if let Some(to) = last_move_to
-                    && square == to
-                {
-                    (
-                        Color32::BLACK,
-                        FontId::proportional(24.0),
-                        format!("{rank} ·"),
-                        format!("  {file} ·"),
-                    )
-                } else {
-                    (
-                        Color32::BLACK,
-                        FontId::proportional(24.0),
-                        format!("{rank}  "),
-                        format!("  {file}  "),
-                    )
-                }
-            };
+            let square = square_from_indices(rank_index, file_index, orientation);
 
-            // TODO files and ranks
-            let rank_x = board_rect.min.x - (MARGIN / 2.0);
-            let rank_y = top + square_size;
-            // let _ = painter.rect_filled(
-            //     Rect::from_min_size(pos2(rank_x, top), vec2(MARGIN / 2.0, square_size)),
-            //     CornerRadius::ZERO,
-            //     Color32::WHITE,
-            // );
-            let _ = painter.text(
-                pos2(rank_x, rank_y),
-                Align2::CENTER_BOTTOM,
-                rank_text,
-                font.clone(),
-                text_color,
-            );
-            let file_y = board_rect.max.y + (MARGIN * 0.6);
-            let file_x = left + (square_size / 2.0);
-            // let _ = painter.rect_filled(
-            //     Rect::from_min_size(
-            //         pos2(file_x, y_offset + board_size + 10.0),
-            //         vec2(square_size, MARGIN),
-            //     ),
-            //     CornerRadius::ZERO,
-            //     Color32::WHITE,
-            // );
-            let _ = painter.text(
-                pos2(file_x, file_y),
-                Align2::CENTER_BOTTOM,
-                file_text,
-                font.clone(),
-                text_color,
-            );
+            if last_move_from == Some(square) || last_move_to == Some(square) {
+                let _ = painter.rect_filled(
+                    square_rect,
+                    CornerRadius::ZERO,
+                    theme.highlights.last_move,
+                );
+            }
 
             highlight_square.map(|highlight| {
                 if highlight == square {
                     let _ = painter.rect_filled(
                         square_rect,
                         CornerRadius::same(2),
-                        Color32::from_rgba_unmultiplied(0, 183, 235, 24),
+                        theme.highlights.selected,
                     );
                 }
             });
@@ -188,7 +234,7 @@ pub fn render_board(
             //     _ => {}
             // }
 
-            if let Some(piece) = game.board().piece_at(square) {
+            if let Some(piece) = position.board.piece_at(square) {
                 let piece_name = format!("{}", piece.char());
                 if let Some(image) = sources.get(piece_name) {
                     image.paint_at(ui, square_rect);
@@ -233,6 +279,34 @@ pub fn render_board(
     //     }
     // }
 
+    // coordinates, inside the board they go over the squares' highlights
+    let font = FontId::new(theme.coordinates.size, theme.coordinates.font.family());
+    for label in coordinate_labels(
+        &board_rect,
+        orientation,
+        theme.coordinates.placement,
+        last_move_to,
+    ) {
+        let _ = painter.text(
+            label.pos,
+            label.align,
+            label.text,
+            font.clone(),
+            theme.coordinates.color,
+        );
+    }
+
+    // weakest first, for the best ones to be on top
+    for arrow in arrows.iter().rev() {
+        render_arrow(
+            &painter,
+            &board_rect,
+            arrow,
+            theme.highlights.arrows,
+            orientation,
+        );
+    }
+
     if let Gesture::Moving(state) = gesture {
         let piece_name = format!("{}", state.piece().char());
         let pos = state.position();
@@ -244,6 +318,51 @@ pub fn render_board(
     }
 }
 
+fn square_center(board_rect: &Rect, square: Square, orientation: Color) -> Pos2 {
+    let square_size = board_rect.width() / 8.0;
+    let (rank_index, file_index) = square_to_indices(square, orientation);
+    pos2(
+        board_rect.min.x + (file_index as f32 + 0.5) * square_size,
+        board_rect.min.y + (rank_index as f32 + 0.5) * square_size,
+    )
+}
+
+fn render_arrow(
+    painter: &egui::Painter,
+    board_rect: &Rect,
+    arrow: &Arrow,
+    color: Color32,
+    orientation: Color,
+) {
+    let square_size = board_rect.width() / 8.0;
+    let weight = arrow.weight.clamp(0.0, 1.0);
+    let start = square_center(board_rect, arrow.from, orientation);
+    let end = square_center(board_rect, arrow.to, orientation);
+    let direction = (end - start).normalized();
+    let normal = direction.rot90();
+
+    let width = square_size * (0.06 + 0.12 * weight);
+    let head_length = square_size * 0.4;
+    let head_width = width + square_size * 0.2;
+    let [red, green, blue, alpha] = color.to_srgba_unmultiplied();
+    let alpha = (60.0 + 140.0 * weight) * alpha as f32 / 255.0;
+    let color = Color32::from_rgba_unmultiplied(red, green, blue, alpha as u8);
+
+    // stop short of the center of the target square, the head points at it
+    let tip = end - direction * square_size * 0.15;
+    let base = tip - direction * head_length;
+    let _ = painter.line_segment([start, base], Stroke::new(width, color));
+    let _ = painter.add(egui::Shape::convex_polygon(
+        vec![
+            tip,
+            base + normal * (head_width / 2.0),
+            base - normal * (head_width / 2.0),
+        ],
+        color,
+        Stroke::NONE,
+    ));
+}
+
 pub fn board_rect(rect: &Rect) -> Rect {
     let whole = if rect.width() > rect.height() {
         let sz = rect.height();
@@ -315,19 +434,42 @@ pub fn file_to_index(file: File) -> usize {
     }
 }
 
-pub fn square_at(container_rect: &Rect, pos: Pos2) -> Option<Square> {
+/// Square at these indices, counted from the top left corner of a board
+/// with `orientation` at the bottom.
+pub fn square_from_indices(rank_index: usize, file_index: usize, orientation: Color) -> Square {
+    let square = Square::from_coords(file_from_index(file_index), rank_from_index(rank_index));
+    if orientation == Color::White {
+        square
+    } else {
+        square.rotate_180()
+    }
+}
+
+/// (rank index, file index) of a square, counted from the top left corner
+/// of a board with `orientation` at the bottom.
+pub fn square_to_indices(square: Square, orientation: Color) -> (usize, usize) {
+    let square = if orientation == Color::White {
+        square
+    } else {
+        square.rotate_180()
+    };
+    (rank_to_index(square.rank()), file_to_index(square.file()))
+}
+
+pub fn square_at(container_rect: &Rect, pos: Pos2, orientation: Color) -> Option<Square> {
     let board_rect = board_rect(container_rect);
     if board_rect.contains(pos) {
         let x = pos.x - board_rect.min.x;
         let y = pos.y - board_rect.min.y;
 
-        let board_x = (x * 8.0 / board_rect.width()).floor();
-        let board_y = (y * 8.0 / board_rect.height()).floor();
-
-        let file = file_from_index(board_x as usize);
-        let rank = rank_from_index(board_y as usize);
+        let board_x = (x * 8.0 / board_rect.width()).floor().min(7.0);
+        let board_y = (y * 8.0 / board_rect.height()).floor().min(7.0);
 
-        Some(Square::from_coords(file, rank))
+        Some(square_from_indices(
+            board_y as usize,
+            board_x as usize,
+            orientation,
+        ))
     } else {
         None
     }
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32, Context, FontId, Ui, pos2};
+use shakmaty::Color;
+use ucui_engine::{ClockTime, TimeControl};
+
+use crate::board::{MARGIN, board_rect};
+
+/// Clocks of both sides, one of them running at most.
+///
+/// Time left is stored when a clock stops, the running one is
+/// computed from when it was started.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    white: Duration,
+    black: Duration,
+    increment: Duration,
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            white: time_control.base,
+            black: time_control.base,
+            increment: time_control.increment,
+            running: None,
+        }
+    }
+
+    fn stored(&mut self, color: Color) -> &mut Duration {
+        match color {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+
+    pub fn remaining(&self, color: Color) -> Duration {
+        let stored = match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        };
+        match self.running {
+            Some((running, since)) if running == color => stored.saturating_sub(since.elapsed()),
+            _ => stored,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Stop whichever clock is running
+    pub fn stop(&mut self) {
+        if let Some((color, _)) = self.running {
+            *self.stored(color) = self.remaining(color);
+        }
+        self.running = None;
+    }
+
+    /// `mover` just played: it gets its increment and the other clock starts.
+    pub fn press(&mut self, mover: Color) {
+        self.stop();
+        let increment = self.increment;
+        *self.stored(mover) += increment;
+        self.running = Some((!mover, Instant::now()));
+    }
+
+    /// Run the clock of `color` instead, without increment (take backs).
+    pub fn switch_to(&mut self, color: Color) {
+        if self.is_running() {
+            self.stop();
+            self.running = Some((color, Instant::now()));
+        }
+    }
+
+    /// The side that ran out of time, if any
+    pub fn flagged(&self) -> Option<Color> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| self.remaining(*color).is_zero())
+    }
+
+    pub fn time(&self) -> ClockTime {
+        ClockTime {
+            white_time: self.remaining(Color::White),
+            black_time: self.remaining(Color::Black),
+            white_increment: self.increment,
+            black_increment: self.increment,
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds % 3600) / 60,
+            seconds % 60
+        )
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        // getting short, show tenths
+        format!("{}.{}", seconds, duration.subsec_millis() / 100)
+    }
+}
+
+/// Draw both clocks in the right margin, next to the turn indicator.
+pub fn render_clock(ctx: &Context, ui: &Ui, clock: &Clock, orientation: Color) {
+    let rect = ui.max_rect();
+    let painter = egui::Painter::new(ctx.clone(), ui.layer_id(), rect);
+    let board_rect = board_rect(&rect);
+    let left = board_rect.max.x + MARGIN * 0.7;
+    let font = FontId::monospace(14.0);
+
+    for color in [Color::White, Color::Black] {
+        let (pos, align) = if color == orientation {
+            (
+                pos2(left, board_rect.max.y - MARGIN / 3.0 - 20.0),
+                Align2::CENTER_BOTTOM,
+            )
+        } else {
+            (
+                pos2(left, board_rect.min.y + MARGIN / 3.0 + 20.0),
+                Align2::CENTER_TOP,
+            )
+        };
+        let remaining = clock.remaining(color);
+        let text_color = if remaining.is_zero() {
+            Color32::DARK_RED
+        } else if clock.running.is_some_and(|(running, _)| running == color) {
+            Color32::BLACK
+        } else {
+            Color32::GRAY
+        };
+        let _ = painter.text(
+            pos,
+            align,
+            format_duration(remaining),
+            font.clone(),
+            text_color,
+        );
+    }
+}
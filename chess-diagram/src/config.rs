@@ -1,18 +1,57 @@
-use clap::Parser;
+use clap::{
+    ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, error::ErrorKind,
+    parser::ValueSource,
+};
+use serde::{Deserialize, Serialize};
 use shakmaty::Color;
+use shakmaty_uci::UciMove;
+use ucui_engine::{SearchLimits, TimeControl, parse_uci_options};
+
+use crate::strength::{PRESETS, find_preset};
+
 // use log::LevelFilter;
-use std::sync::OnceLock;
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::OnceLock, time::Duration};
+
+const CONFIG_FILE: &str = "chess-diagram/config.toml";
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Config {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Configuration file
+    ///
+    /// A TOML file with the same settings as the command line, named as
+    /// its options (engine-depth = 20, uci-option = ["Threads:2"], …),
+    /// and engine profiles. Options given on the command line override
+    /// the file's. Defaults to chess-diagram/config.toml in the XDG
+    /// config directory, if it exists.
+    ///
+    /// Example of a profile, chosen with --engine stockfish:
+    ///
+    /// [engines.stockfish]
+    /// path = "/usr/games/stockfish"
+    /// args = []
+    /// uci-option = ["Threads:4", "Hash:256"]
+    /// depth = 24
+    #[arg(long, value_name = "FILE", global = true, verbatim_doc_comment)]
+    config: Option<String>,
+
+    /// Print the configuration merged from the file and the command line, and exit
+    #[arg(long)]
+    print_config: bool,
+
     /// Start in fullscreen mode
     #[arg(long, action = clap::ArgAction::SetTrue)]
     fullscreen: bool,
 
-    /// Path to a UCI engine
+    /// Path to a UCI engine, or the name of a profile of the configuration file
+    ///
+    /// Without an engine, two players share the board, and one can be
+    /// started later from the keyboard.
     #[arg(long, value_name = "ENGINE")]
-    engine: String,
+    engine: Option<String>,
 
     /// Optional arguments to pass to the engine (separated by ";")
     ///
@@ -33,17 +72,106 @@ pub struct Config {
     #[arg(long, value_name = "ENGINE DEPTH", default_value = "32")]
     engine_depth: u8,
 
+    /// Nodes the engine searches for a move
+    ///
+    /// The search stops at the first of its limits reached.
+    #[arg(long, value_name = "NODES")]
+    engine_nodes: Option<u64>,
+
+    /// Time the engine spends on a move
+    ///
+    /// Taken over the clock, if playing on it. With --engine-infinite or
+    /// --engine-ponder, the engine is stopped after it for its move.
+    #[arg(long, value_name = "MILLISECONDS")]
+    engine_movetime: Option<u64>,
+
+    /// Have the engine look for a mate in MOVES moves
+    #[arg(long, value_name = "MOVES")]
+    engine_mate: Option<u8>,
+
+    /// The engine searches without limit, until stopped for its move
+    ///
+    /// It needs --engine-movetime, the time after which it's stopped.
+    #[arg(long)]
+    engine_infinite: bool,
+
+    /// The engine searches in ponder mode, until stopped for its move
+    ///
+    /// It needs --engine-movetime, the time after which it's stopped.
+    #[arg(long)]
+    engine_ponder: bool,
+
+    /// Move the engine searches, in UCI notation
+    ///
+    /// This argument can be repeated, the engine picking its move among
+    /// these, or any move without them. Moves that aren't legal in the
+    /// position are left out, all being searched if none is.
+    ///
+    /// Example: --engine-searchmoves e2e4 --engine-searchmoves d2d4
+    #[arg(long, value_name = "MOVE")]
+    engine_searchmoves: Vec<String>,
+
     /// UCI option
     ///
     /// This argument can be repeated. UCI options are of the
     /// form "ID[:VALUE]". VALUE can be missing if not needed (buttons).  
     /// See the engine's documentation for available options and their
-    /// default values.
+    /// default values, or its options panel ([O]). Options the engine
+    /// doesn't declare, or values out of their range, are reported and
+    /// left out.
     ///
     /// Example: --uci-option 'Threads:2' --uci-option 'Skill Level:12'
     #[arg(long)]
     uci_option: Vec<String>,
 
+    /// Strength of the engine we play against
+    ///
+    /// One of beginner (~800), novice (~1000), casual (~1200), club
+    /// (~1500), strong (~1800), expert (~2100) or master (~2400), also
+    /// picked from the keyboard ([K]). Engines declaring UCI_Elo play at
+    /// that rating if they can, others search less and not always their
+    /// best move. Analysis is at full strength.
+    ///
+    /// Example: --strength casual
+    #[arg(long, value_name = "PRESET")]
+    strength: Option<String>,
+
+    /// Engine playing white in a match against --engine-black
+    ///
+    /// A path or the name of a profile, as --engine which is then left
+    /// aside. The game plays itself on the board, a move at a time.
+    #[arg(long, value_name = "ENGINE")]
+    engine_white: Option<String>,
+
+    /// Arguments of the white engine (separated by ";")
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+    engine_white_args: Option<String>,
+
+    /// UCI option of the white engine, as --uci-option
+    ///
+    /// This argument can be repeated, options of a profile are kept
+    /// unless set again.
+    #[arg(long, value_name = "ID[:VALUE]")]
+    engine_white_uci_option: Vec<String>,
+
+    /// Engine playing black in a match against --engine-white
+    #[arg(long, value_name = "ENGINE")]
+    engine_black: Option<String>,
+
+    /// Arguments of the black engine (separated by ";")
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+    engine_black_args: Option<String>,
+
+    /// UCI option of the black engine, as --uci-option
+    #[arg(long, value_name = "ID[:VALUE]")]
+    engine_black_uci_option: Vec<String>,
+
+    /// Pause between the moves of a match
+    ///
+    /// On the clock, it's taken from the time of the side to move.
+    #[arg(long, value_name = "MILLISECONDS", default_value = "1000")]
+    move_delay: u64,
+
     /// Opening
     ///
     /// Force moves into this opening, name is a pattern.
@@ -59,49 +187,661 @@ pub struct Config {
     /// Example: --eco A21 --eco A3
     #[arg(long)]
     eco: Vec<String>,
+
+    /// Save the game as PGN
+    ///
+    /// The game is written to FILE when leaving the application,
+    /// and each time it's saved from the keyboard.
+    #[arg(long, value_name = "FILE")]
+    save_pgn: Option<String>,
+
+    /// Load a game from a PGN file
+    ///
+    /// The first game of FILE is loaded for review, the next ones
+    /// can be loaded from the keyboard.
+    #[arg(long, value_name = "FILE")]
+    pgn: Option<String>,
+
+    /// Start from this position
+    ///
+    /// Example: --fen '8/8/8/4k3/8/8/4P3/4K3 w - - 0 1'
+    #[arg(long, value_name = "FEN")]
+    fen: Option<String>,
+
+    /// Lines shown in analysis mode
+    ///
+    /// The engine's best moves are also drawn as arrows on the board.
+    #[arg(long, value_name = "LINES", default_value = "3")]
+    multi_pv: u16,
+
+    /// Play on the clock
+    ///
+    /// Base time in minutes and increment in seconds, the engine
+    /// manages its time instead of searching to a fixed depth.
+    ///
+    /// Example: --time-control 5+3
+    #[arg(long, value_name = "MINUTES+SECONDS")]
+    time_control: Option<TimeControl>,
+
+    /// Piece set
+    ///
+    /// Either a built-in set (leipzig, thin or bold) or a directory with
+    /// an image for each piece, named wK, wQ, …, bN, bP (.svg or .png),
+    /// or a sprite described by a manifest.toml. This argument can be
+    /// repeated, sets are switched from the keyboard starting with the
+    /// first one. SVG pieces are drawn at the size of the squares, SVG
+    /// diagrams keep their own vector pieces.
+    ///
+    /// Example: --piece-set bold --piece-set ~/pieces/cburnett
+    #[arg(long, value_name = "NAME|DIR", global = true)]
+    piece_set: Vec<String>,
+
+    /// Board theme
+    ///
+    /// Either a built-in theme (classic, wood or blue) or a TOML file
+    /// setting the background, the light and dark squares (a color, an
+    /// image next to the file, or "hatching"), the frame, the
+    /// coordinates and the highlights, anything missing being classic.
+    /// This argument can be repeated, themes are switched from the
+    /// keyboard starting with the first one.
+    ///
+    /// Example: --theme wood --theme ~/themes/marble.toml
+    #[arg(long, value_name = "NAME|FILE", global = true)]
+    theme: Vec<String>,
+
+    /// the configuration file that was read
+    #[arg(skip)]
+    file: Option<PathBuf>,
+
+    /// profile `engine` was taken from
+    #[arg(skip)]
+    engine_profile: Option<String>,
+
+    #[arg(skip)]
+    engines: BTreeMap<String, EngineProfile>,
+}
+
+/// An engine and how to run it, picked by its name with --engine.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct EngineProfile {
+    path: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    uci_option: Vec<String>,
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+    mate: Option<u8>,
+}
+
+/// The settings of the configuration file, named as the command line's options.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    fullscreen: Option<bool>,
+    engine: Option<String>,
+    engine_args: Option<String>,
+    engine_color: Option<String>,
+    engine_depth: Option<u8>,
+    engine_nodes: Option<u64>,
+    engine_movetime: Option<u64>,
+    engine_mate: Option<u8>,
+    engine_infinite: Option<bool>,
+    engine_ponder: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    engine_searchmoves: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    uci_option: Vec<String>,
+    strength: Option<String>,
+    engine_white: Option<String>,
+    engine_white_args: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    engine_white_uci_option: Vec<String>,
+    engine_black: Option<String>,
+    engine_black_args: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    engine_black_uci_option: Vec<String>,
+    move_delay: Option<u64>,
+    opening: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    eco: Vec<String>,
+    save_pgn: Option<String>,
+    multi_pv: Option<u16>,
+    time_control: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    piece_set: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    theme: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    engines: BTreeMap<String, EngineProfile>,
+}
+
+/// $XDG_CONFIG_HOME/chess-diagram/config.toml, or under ~/.config
+fn default_config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// UCI options from first to last, a later value for an ID replacing an earlier one.
+fn merge_uci_options(lists: &[&[String]]) -> Vec<String> {
+    let id = |option: &String| option.split(':').next().unwrap_or_default().to_string();
+    let mut options: Vec<String> = Vec::new();
+    for option in lists.iter().flat_map(|list| list.iter()) {
+        match options.iter_mut().find(|known| id(known) == id(option)) {
+            Some(known) => *known = option.clone(),
+            None => options.push(option.clone()),
+        }
+    }
+    options
+}
+
+impl Config {
+    /// Take what the command line doesn't set from the file, and the engine
+    /// from its profile.
+    fn merge(&mut self, file: ConfigFile, matches: &ArgMatches) -> Result<(), String> {
+        let from_command_line =
+            |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if !from_command_line("fullscreen")
+            && let Some(fullscreen) = file.fullscreen
+        {
+            self.fullscreen = fullscreen;
+        }
+        if self.engine.is_none() {
+            self.engine = file.engine;
+        }
+        if self.engine_args.is_none() {
+            self.engine_args = file.engine_args;
+        }
+        if !from_command_line("engine_color")
+            && let Some(color) = file.engine_color
+        {
+            self.engine_color = color
+                .parse()
+                .map_err(|_| format!("invalid engine-color '{color}'"))?;
+        }
+        if !from_command_line("engine_depth")
+            && let Some(depth) = file.engine_depth
+        {
+            self.engine_depth = depth;
+        }
+        if self.engine_nodes.is_none() {
+            self.engine_nodes = file.engine_nodes;
+        }
+        if self.engine_movetime.is_none() {
+            self.engine_movetime = file.engine_movetime;
+        }
+        if self.engine_mate.is_none() {
+            self.engine_mate = file.engine_mate;
+        }
+        if !from_command_line("engine_infinite")
+            && let Some(infinite) = file.engine_infinite
+        {
+            self.engine_infinite = infinite;
+        }
+        if !from_command_line("engine_ponder")
+            && let Some(ponder) = file.engine_ponder
+        {
+            self.engine_ponder = ponder;
+        }
+        if self.engine_searchmoves.is_empty() {
+            self.engine_searchmoves = file.engine_searchmoves;
+        }
+        if let Some(move_) = self
+            .engine_searchmoves
+            .iter()
+            .find(|move_| move_.parse::<UciMove>().is_err())
+        {
+            return Err(format!("invalid engine-searchmoves '{move_}'"));
+        }
+        if self.strength.is_none() {
+            self.strength = file.strength;
+        }
+        if let Some(strength) = &self.strength
+            && find_preset(strength).is_none()
+        {
+            let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+            return Err(format!(
+                "unknown strength '{strength}', one of {}",
+                names.join(", ")
+            ));
+        }
+        if self.engine_white.is_none() {
+            self.engine_white = file.engine_white;
+        }
+        if self.engine_white_args.is_none() {
+            self.engine_white_args = file.engine_white_args;
+        }
+        if self.engine_white_uci_option.is_empty() {
+            self.engine_white_uci_option = file.engine_white_uci_option;
+        }
+        if self.engine_black.is_none() {
+            self.engine_black = file.engine_black;
+        }
+        if self.engine_black_args.is_none() {
+            self.engine_black_args = file.engine_black_args;
+        }
+        if self.engine_black_uci_option.is_empty() {
+            self.engine_black_uci_option = file.engine_black_uci_option;
+        }
+        if self.engine_white.is_some() != self.engine_black.is_some() {
+            return Err(String::from(
+                "a match needs both --engine-white and --engine-black",
+            ));
+        }
+        if !from_command_line("move_delay")
+            && let Some(move_delay) = file.move_delay
+        {
+            self.move_delay = move_delay;
+        }
+        if self.opening.is_none() {
+            self.opening = file.opening;
+        }
+        if self.eco.is_empty() {
+            self.eco = file.eco;
+        }
+        if self.save_pgn.is_none() {
+            self.save_pgn = file.save_pgn;
+        }
+        if !from_command_line("multi_pv")
+            && let Some(multi_pv) = file.multi_pv
+        {
+            self.multi_pv = multi_pv;
+        }
+        if self.time_control.is_none()
+            && let Some(time_control) = file.time_control
+        {
+            self.time_control = Some(
+                time_control
+                    .parse()
+                    .map_err(|err| format!("time-control: {err}"))?,
+            );
+        }
+        if self.piece_set.is_empty() {
+            self.piece_set = file.piece_set;
+        }
+        if self.theme.is_empty() {
+            self.theme = file.theme;
+        }
+
+        // a profile is more specific than the file, less than the command line
+        let mut profile_options: &[String] = &[];
+        if let Some(name) = self.engine.clone()
+            && let Some(profile) = file.engines.get(&name)
+        {
+            self.engine = Some(profile.path.clone());
+            if !from_command_line("engine_args") && !profile.args.is_empty() {
+                self.engine_args = Some(profile.args.join(";"));
+            }
+            if !from_command_line("engine_depth")
+                && let Some(depth) = profile.depth
+            {
+                self.engine_depth = depth;
+            }
+            if !from_command_line("engine_nodes") && profile.nodes.is_some() {
+                self.engine_nodes = profile.nodes;
+            }
+            if !from_command_line("engine_movetime") && profile.movetime.is_some() {
+                self.engine_movetime = profile.movetime;
+            }
+            if !from_command_line("engine_mate") && profile.mate.is_some() {
+                self.engine_mate = profile.mate;
+            }
+            profile_options = &profile.uci_option;
+            self.engine_profile = Some(name);
+        }
+        self.uci_option = merge_uci_options(&[&file.uci_option, profile_options, &self.uci_option]);
+        self.engines = file.engines;
+        if (self.engine_infinite || self.engine_ponder) && self.engine_movetime.is_none() {
+            return Err(String::from(
+                "engine-infinite and engine-ponder need engine-movetime, to stop the search for a move",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The settings in effect, as a configuration file.
+    fn to_toml(&self) -> String {
+        let file = ConfigFile {
+            fullscreen: Some(self.fullscreen),
+            engine: self.engine.clone(),
+            engine_args: self.engine_args.clone(),
+            engine_color: Some(self.engine_color.to_string()),
+            engine_depth: Some(self.engine_depth),
+            engine_nodes: self.engine_nodes,
+            engine_movetime: self.engine_movetime,
+            engine_mate: self.engine_mate,
+            engine_infinite: Some(self.engine_infinite),
+            engine_ponder: Some(self.engine_ponder),
+            engine_searchmoves: self.engine_searchmoves.clone(),
+            uci_option: self.uci_option.clone(),
+            strength: self.strength.clone(),
+            engine_white: self.engine_white.clone(),
+            engine_white_args: self.engine_white_args.clone(),
+            engine_white_uci_option: self.engine_white_uci_option.clone(),
+            engine_black: self.engine_black.clone(),
+            engine_black_args: self.engine_black_args.clone(),
+            engine_black_uci_option: self.engine_black_uci_option.clone(),
+            move_delay: Some(self.move_delay),
+            opening: self.opening.clone(),
+            eco: self.eco.clone(),
+            save_pgn: self.save_pgn.clone(),
+            multi_pv: Some(self.multi_pv),
+            time_control: self
+                .time_control
+                .map(|time_control| time_control.to_string()),
+            piece_set: self.piece_set.clone(),
+            theme: self.theme.clone(),
+            engines: BTreeMap::new(),
+        };
+        let mut content = match &self.file {
+            Some(path) => format!("# {} and the command line\n", path.display()),
+            None => String::from("# the command line, without a configuration file\n"),
+        };
+        if let Some(profile) = &self.engine_profile {
+            content.push_str(&format!("# engine from the profile {profile}\n"));
+        }
+        content.push_str(&toml::to_string(&file).unwrap_or_default());
+        // profiles last, as they're tables
+        if !self.engines.is_empty() {
+            let engines = BTreeMap::from([("engines", &self.engines)]);
+            content.push('\n');
+            content.push_str(&toml::to_string(&engines).unwrap_or_default());
+        }
+        content
+    }
+}
+
+/// Commands running without a window
+#[derive(Subcommand, Clone)]
+pub enum Command {
+    /// Write a diagram of a position as SVG
+    Svg {
+        /// Position to draw, the initial one if missing
+        #[arg(long, value_name = "FEN")]
+        fen: Option<String>,
+
+        /// Title above the board
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Color at the bottom of the board
+        #[arg(long, value_name = "COLOR", default_value = "white")]
+        orientation: Color,
+
+        /// Width and height of the diagram
+        #[arg(long, value_name = "PIXELS", default_value = "600")]
+        size: f32,
+
+        /// Where to write the diagram
+        #[arg(long, short, value_name = "FILE")]
+        output: String,
+    },
+
+    /// Render a diagram of a position as PNG
+    Png {
+        /// Position to draw, the initial one if missing
+        #[arg(long, value_name = "FEN", conflicts_with = "pgn")]
+        fen: Option<String>,
+
+        /// Take the position from a game in a PGN file
+        #[arg(long, value_name = "FILE")]
+        pgn: Option<String>,
+
+        /// Game to take in the PGN file, from 1
+        #[arg(long, value_name = "N", default_value = "1", requires = "pgn")]
+        game: usize,
+
+        /// Half-moves to play from the start of the game, all of them if missing
+        #[arg(long, value_name = "N", requires = "pgn")]
+        ply: Option<usize>,
+
+        /// Title above the board
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Color at the bottom of the board
+        #[arg(long, value_name = "COLOR", default_value = "white")]
+        orientation: Color,
+
+        /// Width and height of the image
+        #[arg(long, value_name = "PIXELS", default_value = "600")]
+        size: u32,
+
+        /// Where to write the image
+        #[arg(long, short, value_name = "FILE")]
+        output: String,
+    },
+
+    /// Write diagrams for the games of a PGN file, or for their marked moves
+    Batch {
+        /// PGN file to read
+        #[arg(long, value_name = "FILE")]
+        pgn: String,
+
+        /// One diagram for each move with a NAG or this text in its comment,
+        /// instead of one for each game
+        #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = "[#]")]
+        marked: Option<String>,
+
+        /// Half-moves to play from the start of each game, all of them if missing
+        #[arg(long, value_name = "N", conflicts_with = "marked")]
+        ply: Option<usize>,
+
+        /// Color at the bottom of the boards
+        #[arg(long, value_name = "COLOR", default_value = "white")]
+        orientation: Color,
+
+        /// Width and height of the diagrams
+        #[arg(long, value_name = "PIXELS", default_value = "600")]
+        size: u32,
+
+        /// Names of the files, where {game}, {ply}, {move}, {white}, {black},
+        /// {event}, {round} and {date} are replaced, ending with .svg or .png
+        #[arg(
+            long,
+            short,
+            value_name = "TEMPLATE",
+            default_value = "diagram-{game}-{ply}.png"
+        )]
+        output: String,
+    },
 }
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
 
+/// The command line over the configuration file.
+fn load_config() -> Config {
+    let matches = Config::command().get_matches();
+    let mut config = Config::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let exit = |message: String| -> ! {
+        Config::command()
+            .error(ErrorKind::InvalidValue, message)
+            .exit()
+    };
+
+    // the file asked for has to be there, the default one may not
+    config.file = match &config.config {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_config_path().filter(|path| path.is_file()),
+    };
+    let file = match &config.file {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))
+            .and_then(|content| {
+                toml::from_str::<ConfigFile>(&content)
+                    .map_err(|err| format!("invalid {}: {err}", path.display()))
+            })
+            .unwrap_or_else(|err| exit(err)),
+        None => ConfigFile::default(),
+    };
+    if let Err(err) = config.merge(file, &matches) {
+        exit(match &config.file {
+            Some(path) => format!("{}: {err}", path.display()),
+            None => err,
+        });
+    }
+    config
+}
+
 pub fn config() -> &'static Config {
-    CONFIG.get_or_init(Config::parse)
+    CONFIG.get_or_init(load_config)
+}
+
+pub fn get_print_config() -> bool {
+    config().print_config
+}
+
+/// The merged configuration, to be printed.
+pub fn get_config_toml() -> String {
+    config().to_toml()
 }
 
 pub fn get_fullscreen() -> bool {
     config().fullscreen
 }
 
-pub fn get_engine() -> String {
-    config().engine.clone()
+pub fn get_command() -> Option<Command> {
+    config().command.clone()
+}
+
+/// How to run an engine
+#[derive(Clone)]
+pub struct EngineSetup {
+    pub path: String,
+    pub args: Option<Vec<String>>,
+    pub options: Vec<(String, Option<String>)>,
+    pub limits: SearchLimits,
+}
+
+/// The engine asked for, if any.
+pub fn get_engine() -> Option<EngineSetup> {
+    config().engine.clone().map(|path| EngineSetup {
+        path,
+        args: get_engine_args(),
+        options: get_engine_options(),
+        limits: get_search_limits(),
+    })
+}
+
+/// An engine started from the UI, by the name of a profile or its path.
+pub fn get_engine_by_name(name: &str) -> EngineSetup {
+    match config().engines.get(name) {
+        Some(profile) => EngineSetup {
+            path: profile.path.clone(),
+            args: (!profile.args.is_empty()).then(|| profile.args.clone()),
+            options: parse_uci_options(&profile.uci_option),
+            limits: {
+                let mut limits = get_search_limits();
+                limits.depth = profile.depth.or(limits.depth);
+                limits.nodes = profile.nodes.or(limits.nodes);
+                limits.movetime = profile
+                    .movetime
+                    .map(Duration::from_millis)
+                    .or(limits.movetime);
+                limits.mate = profile.mate.or(limits.mate);
+                limits
+            },
+        },
+        None => EngineSetup {
+            path: name.to_string(),
+            args: None,
+            options: Vec::new(),
+            limits: get_search_limits(),
+        },
+    }
+}
+
+/// A side of a match, its profile's arguments and options completed
+/// by the command line.
+fn match_engine(name: &str, args: &Option<String>, uci_option: &[String]) -> EngineSetup {
+    let mut setup = get_engine_by_name(name);
+    if let Some(args) = args {
+        setup.args = Some(split_args(args));
+    }
+    let profile_options = config()
+        .engines
+        .get(name)
+        .map(|profile| profile.uci_option.as_slice())
+        .unwrap_or_default();
+    setup.options = parse_uci_options(&merge_uci_options(&[profile_options, uci_option]));
+    setup
+}
+
+/// The white and black engines of a match, if one is asked for.
+pub fn get_engine_match() -> Option<(EngineSetup, EngineSetup)> {
+    let config = config();
+    let white = config.engine_white.as_ref()?;
+    let black = config.engine_black.as_ref()?;
+    Some((
+        match_engine(
+            white,
+            &config.engine_white_args,
+            &config.engine_white_uci_option,
+        ),
+        match_engine(
+            black,
+            &config.engine_black_args,
+            &config.engine_black_uci_option,
+        ),
+    ))
+}
+
+pub fn get_move_delay() -> Duration {
+    Duration::from_millis(config().move_delay)
+}
+
+/// Names of the engine profiles of the configuration file
+pub fn get_engine_profiles() -> Vec<String> {
+    config().engines.keys().cloned().collect()
 }
 
 pub fn get_engine_args() -> Option<Vec<String>> {
-    config()
-        .engine_args
-        .clone()
-        .map(|args| args.split(";").map(|arg| arg.to_string()).collect())
+    config().engine_args.as_deref().map(split_args)
+}
+
+fn split_args(args: &str) -> Vec<String> {
+    args.split(";").map(|arg| arg.to_string()).collect()
 }
 
 pub fn get_engine_color() -> Color {
     config().engine_color
 }
-pub fn get_engine_depth() -> u8 {
-    config().engine_depth
+/// How far the engine searches for its moves
+pub fn get_search_limits() -> SearchLimits {
+    let config = config();
+    SearchLimits {
+        depth: Some(config.engine_depth),
+        nodes: config.engine_nodes,
+        movetime: config.engine_movetime.map(Duration::from_millis),
+        mate: config.engine_mate,
+        infinite: config.engine_infinite,
+        ponder: config.engine_ponder,
+        searchmoves: config
+            .engine_searchmoves
+            .iter()
+            .filter_map(|move_| move_.parse().ok())
+            .collect(),
+        time: None,
+    }
 }
 
-pub fn get_engine_options() -> Vec<(String, Option<String>)> {
+/// The preset the engine starts playing at, by its name
+pub fn get_strength() -> Option<&'static str> {
     config()
-        .uci_option
-        .iter()
-        .map(|opt| {
-            let parts: Vec<String> = opt.split(":").take(2).map(|s| s.to_string()).collect();
-            match parts.len() {
-                0 => (String::new(), None),
-                1 => (parts[0].clone(), None),
-                _ => (parts[0].clone(), Some(parts[1].clone())),
-            }
-        })
-        .collect()
+        .strength
+        .as_deref()
+        .and_then(find_preset)
+        .map(|preset| preset.name)
+}
+
+pub fn get_engine_options() -> Vec<(String, Option<String>)> {
+    parse_uci_options(&config().uci_option)
 }
 
 pub fn get_eco_codes() -> Vec<String> {
@@ -112,6 +852,34 @@ pub fn get_opening() -> Option<String> {
     config().opening.clone()
 }
 
+pub fn get_save_pgn() -> Option<String> {
+    config().save_pgn.clone()
+}
+
+pub fn get_pgn() -> Option<String> {
+    config().pgn.clone()
+}
+
+pub fn get_fen() -> Option<String> {
+    config().fen.clone()
+}
+
+pub fn get_multi_pv() -> u16 {
+    config().multi_pv
+}
+
+pub fn get_time_control() -> Option<TimeControl> {
+    config().time_control
+}
+
+pub fn get_piece_sets() -> Vec<String> {
+    config().piece_set.clone()
+}
+
+pub fn get_themes() -> Vec<String> {
+    config().theme.clone()
+}
+
 // #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 // pub enum LogLevel {
 //     /// A level lower than all log levels.
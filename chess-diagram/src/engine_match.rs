@@ -0,0 +1,118 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use egui::Context;
+use shakmaty::{Color, Position};
+use ucui_engine::{EngineError, SearchId};
+
+use crate::{
+    config::EngineSetup,
+    game::{GameState, Players},
+    proxy::{Proxy, start_engine},
+};
+
+/// Two engines playing each other on the board, a move at a time.
+pub struct EngineMatch {
+    white: Proxy,
+    black: Proxy,
+    /// pause before each move, for the game to be watched
+    delay: Duration,
+    pub paused: bool,
+    /// position on the board, and since when
+    position: String,
+    since: Instant,
+    /// the search of the side to move in this position
+    asked: Option<SearchId>,
+}
+
+impl EngineMatch {
+    pub fn start(
+        white: EngineSetup,
+        black: EngineSetup,
+        delay: Duration,
+        state: Arc<RwLock<GameState>>,
+        ctx: Arc<Mutex<Context>>,
+    ) -> Self {
+        Self {
+            white: start_engine(white, state.clone(), ctx.clone()),
+            black: start_engine(black, state, ctx),
+            delay,
+            paused: false,
+            position: String::new(),
+            since: Instant::now(),
+            asked: None,
+        }
+    }
+
+    fn engine(&self, color: Color) -> &Proxy {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    pub fn players(&self) -> Players {
+        Players::Engines {
+            white: self.white.name(),
+            black: self.black.name(),
+        }
+    }
+
+    pub fn new_game(&mut self) {
+        self.white.new_game();
+        self.black.new_game();
+        self.asked = None;
+    }
+
+    /// Interrupt the search of the side to move, to be asked again
+    /// once the position settles.
+    pub fn stop(&self) {
+        self.white.stop();
+        self.black.stop();
+    }
+
+    /// What went wrong with either engine since last asked
+    pub fn take_error(&self) -> Option<(Color, EngineError)> {
+        self.white
+            .take_error()
+            .map(|err| (Color::White, err))
+            .or_else(|| self.black.take_error().map(|err| (Color::Black, err)))
+    }
+
+    /// Have the side to move play once the delay is over, book moves of a
+    /// forced opening going without asking. Returns how long is left to
+    /// wait before it's time.
+    pub fn step(&mut self, game_state: &mut GameState) -> Option<Duration> {
+        if self.paused || game_state.outcome().is_some() {
+            return None;
+        }
+        // a move was played, or the position changed under us
+        let fen = game_state.fen();
+        if fen != self.position {
+            self.position = fen;
+            self.since = Instant::now();
+            self.asked = None;
+        }
+        let turn = game_state.game.turn();
+        if let Some(search) = self.asked {
+            // waiting on the move, unless the search got interrupted
+            if self.engine(turn).is_searching(search) {
+                return None;
+            }
+            self.asked = None;
+            self.since = Instant::now();
+        }
+        let elapsed = self.since.elapsed();
+        if elapsed < self.delay {
+            return Some(self.delay - elapsed);
+        }
+        if let Some((move_, _)) = game_state.openings.find_move(&game_state.game) {
+            game_state.make_move(move_);
+            return Some(self.delay);
+        }
+        self.asked = Some(self.engine(turn).play(game_state));
+        None
+    }
+}
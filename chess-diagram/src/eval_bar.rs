@@ -0,0 +1,92 @@
+use egui::{
+    Align2, Color32, Context, CornerRadius, FontId, Id, Rect, Stroke, StrokeKind, Ui, pos2,
+};
+use shakmaty::Color;
+use ucui_engine::Score;
+
+use crate::board::board_rect;
+
+const BAR_OFFSET: f32 = 12.0;
+const BAR_WIDTH: f32 = 10.0;
+const ANIMATION_TIME: f32 = 0.4;
+
+/// Chances for White to win, from 0 to 1, for a score seen by `pov`.
+///
+/// Centipawns go through the same logistic curve as lichess, a mate is a sure thing.
+pub fn win_probability(score: &Score, pov: Color) -> Option<f32> {
+    let white_probability = |probability: f32| {
+        if pov == Color::White {
+            probability
+        } else {
+            1.0 - probability
+        }
+    };
+    match score {
+        Score::CentiPawns { score, .. } => Some(white_probability(
+            1.0 / (1.0 + (-0.003_682_08 * *score as f32).exp()),
+        )),
+        Score::Mate { moves } if *moves > 0 => Some(white_probability(1.0)),
+        Score::Mate { .. } => Some(white_probability(0.0)),
+        Score::None => None,
+    }
+}
+
+fn label(score: &Score) -> Option<String> {
+    match score {
+        Score::CentiPawns { score, .. } => Some(format!("{:.1}", score.abs() as f32 / 100.0)),
+        Score::Mate { moves } => Some(format!("M{}", moves.unsigned_abs())),
+        Score::None => None,
+    }
+}
+
+/// Draw the evaluation along the right side of the board, White's share
+/// of the bar growing from White's side.
+pub fn render_eval_bar(ctx: &Context, ui: &Ui, score: &Score, pov: Color, orientation: Color) {
+    // between two evaluations, the bar stays where it was
+    let id = Id::new("eval_bar");
+    let Some(target) =
+        win_probability(score, pov).or_else(|| ctx.data(|data| data.get_temp::<f32>(id)))
+    else {
+        return;
+    };
+    ctx.data_mut(|data| data.insert_temp(id, target));
+    let probability = ctx.animate_value_with_time(id, target, ANIMATION_TIME);
+
+    let rect = ui.max_rect();
+    let painter = egui::Painter::new(ctx.clone(), ui.layer_id(), rect);
+    let board_rect = board_rect(&rect);
+    let bar = Rect::from_min_max(
+        pos2(board_rect.max.x + BAR_OFFSET, board_rect.min.y),
+        pos2(board_rect.max.x + BAR_OFFSET + BAR_WIDTH, board_rect.max.y),
+    );
+    let white_height = bar.height() * probability;
+    let white_part = if orientation == Color::White {
+        Rect::from_min_max(pos2(bar.min.x, bar.max.y - white_height), bar.max)
+    } else {
+        Rect::from_min_max(bar.min, pos2(bar.max.x, bar.min.y + white_height))
+    };
+
+    let _ = painter.rect_filled(bar, CornerRadius::ZERO, Color32::DARK_GRAY);
+    let _ = painter.rect_filled(white_part, CornerRadius::ZERO, Color32::WHITE);
+    let _ = painter.rect_stroke(
+        bar,
+        CornerRadius::ZERO,
+        Stroke::new(1.0, Color32::BLACK),
+        StrokeKind::Outside,
+    );
+
+    // the score goes at the end of the side that's better
+    if let Some(text) = label(score) {
+        let better = if target >= 0.5 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let (pos, align) = if better == orientation {
+            (pos2(bar.min.x, bar.max.y + 12.0), Align2::LEFT_TOP)
+        } else {
+            (pos2(bar.min.x, bar.min.y - 12.0), Align2::LEFT_BOTTOM)
+        };
+        let _ = painter.text(pos, align, text, FontId::monospace(11.0), Color32::BLACK);
+    }
+}
@@ -0,0 +1,174 @@
+use std::{fs, io, str::FromStr};
+
+use shakmaty::{Color, EnPassantMode, Move, Position, Setup, fen::Fen};
+use ucui_utils::parse_pgn;
+
+use crate::{
+    batch::run_batch,
+    config::{Command, get_piece_sets, get_themes},
+    piece_set::PieceSet,
+    png::render_png,
+    svg::render_svg,
+    theme::Theme,
+};
+
+/// Write the position as an SVG diagram.
+pub fn write_svg(
+    path: &str,
+    position: &Setup,
+    theme: &Theme,
+    title: Option<&str>,
+    orientation: Color,
+    size: f32,
+) -> io::Result<()> {
+    fs::write(path, render_svg(position, theme, title, orientation, size))
+}
+
+/// Write the position as a PNG image.
+#[allow(clippy::too_many_arguments)]
+pub fn write_png(
+    path: &str,
+    position: &Setup,
+    piece_set: &PieceSet,
+    theme: &Theme,
+    last_move: Option<&Move>,
+    title: Option<&str>,
+    orientation: Color,
+    size: u32,
+) -> image::ImageResult<()> {
+    render_png(
+        position,
+        piece_set,
+        theme,
+        last_move,
+        title,
+        orientation,
+        size,
+    )
+    .save_with_format(path, image::ImageFormat::Png)
+}
+
+/// Diagrams are drawn as given, legal or not.
+fn setup_from_fen(fen: Option<String>) -> Result<Setup, String> {
+    match fen {
+        Some(fen) => Fen::from_str(&fen)
+            .map(|fen| fen.into_setup())
+            .map_err(|err| format!("invalid FEN '{fen}': {err}")),
+        None => Ok(Setup::initial()),
+    }
+}
+
+/// The position after `ply` half-moves of the `game`th game of a PGN file,
+/// and the move that led to it.
+fn setup_from_pgn(
+    path: &str,
+    game: usize,
+    ply: Option<usize>,
+) -> Result<(Setup, Option<Move>), String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("failed to read {path}: {err}"))?;
+    let games = parse_pgn(&content).map_err(|err| format!("invalid PGN in {path}: {err}"))?;
+    let pgn_game = game
+        .checked_sub(1)
+        .and_then(|index| games.get(index))
+        .ok_or(format!("no game {game} in {path}, it has {}", games.len()))?;
+    let ply = ply.unwrap_or(pgn_game.moves.len());
+    if ply > pgn_game.moves.len() {
+        return Err(format!(
+            "game {game} in {path} has only {} half-moves",
+            pgn_game.moves.len()
+        ));
+    }
+
+    let mut position = pgn_game.start.clone();
+    for move_ in &pgn_game.moves[..ply] {
+        position.play_unchecked(move_);
+    }
+    let last_move = ply
+        .checked_sub(1)
+        .map(|index| pgn_game.moves[index].clone());
+    Ok((position.into_setup(EnPassantMode::Legal), last_move))
+}
+
+/// The first piece set asked for, the built-in one otherwise.
+fn piece_set() -> Result<PieceSet, String> {
+    match get_piece_sets().first() {
+        Some(name) => PieceSet::load(name),
+        None => Ok(PieceSet::builtin()),
+    }
+}
+
+/// The first theme asked for, the classic one otherwise.
+fn theme() -> Result<Theme, String> {
+    match get_themes().first() {
+        Some(name) => Theme::load(name),
+        None => Ok(Theme::classic()),
+    }
+}
+
+/// Run a command without opening a window.
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Svg {
+            fen,
+            title,
+            orientation,
+            size,
+            output,
+        } => {
+            let position = setup_from_fen(fen)?;
+            write_svg(
+                &output,
+                &position,
+                &theme()?,
+                title.as_deref(),
+                orientation,
+                size,
+            )
+            .map_err(|err| format!("failed to write {output}: {err}"))
+        }
+        Command::Png {
+            fen,
+            pgn,
+            game,
+            ply,
+            title,
+            orientation,
+            size,
+            output,
+        } => {
+            let (position, last_move) = match pgn {
+                Some(pgn) => setup_from_pgn(&pgn, game, ply)?,
+                None => (setup_from_fen(fen)?, None),
+            };
+            write_png(
+                &output,
+                &position,
+                &piece_set()?,
+                &theme()?,
+                last_move.as_ref(),
+                title.as_deref(),
+                orientation,
+                size,
+            )
+            .map_err(|err| format!("failed to write {output}: {err}"))
+        }
+        Command::Batch {
+            pgn,
+            marked,
+            ply,
+            orientation,
+            size,
+            output,
+        } => run_batch(
+            &pgn,
+            marked.as_deref(),
+            ply,
+            &output,
+            &piece_set()?,
+            &theme()?,
+            orientation,
+            size,
+        ),
+    }
+}
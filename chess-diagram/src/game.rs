@@ -1,55 +1,281 @@
 use std::{collections::HashMap, str::FromStr};
 
 use rand::seq::IndexedRandom;
-use shakmaty::{Chess, Color, FromSetup, Move, Position, fen::Fen};
+use shakmaty::{Chess, Color, FromSetup, Move, Outcome, Position, fen::Fen};
 use ucui_eco::{
     Eco, find_eco_from_moves, get_openings_table, lookup_eco_from_code, lookup_eco_from_name,
 };
-use ucui_engine::Score;
+use ucui_engine::{Score, SearchInfo};
+use ucui_utils::PgnGame;
 
-use crate::config::{get_eco_codes, get_opening};
+use crate::{
+    clock::Clock,
+    config::{get_eco_codes, get_opening, get_time_control},
+};
+
+const PLAYER_NAME: &str = "Player";
+
+/// Who's at the board, for the tags of a PGN export
+pub enum Players {
+    Humans,
+    /// against us, playing `engine_color`
+    Engine(String),
+    /// a match between two engines
+    Engines {
+        white: String,
+        black: String,
+    },
+}
 
 pub struct GameState {
+    pub start: Chess,
     pub game: Chess,
     pub moves: Vec<Move>,
+    undone: Vec<Move>,
+    /// the engine's score after each move, if it gave one
+    scores: Vec<Score>,
+    undone_scores: Vec<Score>,
     pub engine_color: Color,
     pub openings: Openings,
     pub opening: Option<Eco>,
     pub score: Score,
+    /// lines of the running analysis, best first
+    pub analysis: Vec<SearchInfo>,
+    pub clock: Option<Clock>,
 }
 
 impl GameState {
-    pub fn new(color: Color, position: Option<String>) -> Self {
-        Self {
+    pub fn new(color: Color, position: Option<String>) -> Result<Self, String> {
+        let start: Chess = match position {
+            Some(fen_string) => position_from_fen(&fen_string)?,
+            None => Chess::default(),
+        };
+        Ok(Self {
             engine_color: color,
+            start: start.clone(),
+            game: start,
             moves: Vec::new(),
-            game: position
-                .and_then(|fen_string| Fen::from_str(&fen_string).ok())
-                .and_then(|fen| {
-                    Chess::from_setup(fen.into_setup(), shakmaty::CastlingMode::Standard).ok()
-                })
-                .unwrap_or_default(),
+            undone: Vec::new(),
+            scores: Vec::new(),
+            undone_scores: Vec::new(),
             openings: Openings::new(),
             opening: None,
             score: Score::None,
-        }
+            analysis: Vec::new(),
+            clock: get_time_control().map(Clock::new),
+        })
     }
 
-    pub fn make_move(&mut self, move_: Move) {
+    /// Start over from the initial position
+    pub fn reset(&mut self) {
+        self.start = Chess::new();
+        self.game = Chess::new();
+        self.moves = Vec::new();
+        self.undone = Vec::new();
+        self.scores = Vec::new();
+        self.undone_scores = Vec::new();
+        self.opening = None;
+        self.clock = get_time_control().map(Clock::new);
+        self.clear_score();
+    }
+
+    /// Start over from the position given as FEN
+    pub fn set_position(&mut self, fen_string: &str) -> Result<(), String> {
+        let start = position_from_fen(fen_string)?;
+        self.start = start.clone();
+        self.game = start;
+        self.moves = Vec::new();
+        self.undone = Vec::new();
+        self.scores = Vec::new();
+        self.undone_scores = Vec::new();
+        self.opening = None;
+        self.clock = get_time_control().map(Clock::new);
+        self.clear_score();
+        Ok(())
+    }
+
+    /// Set up a game to be replayed, from its first move.
+    pub fn load(&mut self, pgn: &PgnGame) {
+        self.start = pgn.start.clone();
+        self.game = pgn.start.clone();
+        self.moves = Vec::new();
+        self.undone = pgn.moves.iter().rev().cloned().collect();
+        self.scores = Vec::new();
+        self.undone_scores = Vec::new();
+        self.opening = None;
+        // replayed games are not on the clock
+        self.clock = None;
+        self.clear_score();
+    }
+
+    fn push_move(&mut self, move_: Move) -> bool {
         if let Ok(new_game) = self.game.clone().play(&move_) {
             self.moves.push(move_.clone());
+            self.scores.push(Score::None);
             self.opening = find_eco_from_moves(&self.moves).cloned();
             self.game = new_game;
-        };
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Play a move, it drops whatever could have been redone.
+    ///
+    /// Nothing is played once the game is over, flag falls included.
+    pub fn make_move(&mut self, move_: Move) {
+        if self.outcome().is_some() {
+            return;
+        }
+        let mover = self.game.turn();
+        if self.push_move(move_) {
+            self.undone.clear();
+            self.undone_scores.clear();
+            if let Some(clock) = self.clock.as_mut() {
+                clock.press(mover);
+                if self.game.is_game_over() {
+                    clock.stop();
+                }
+            }
+        }
+    }
+
+    /// Take back the last move, it's kept around for `redo`.
+    pub fn undo(&mut self) -> Option<Move> {
+        let move_ = self.moves.pop()?;
+        let score = self.scores.pop().unwrap_or(Score::None);
+        let mut game = self.start.clone();
+        for m in self.moves.iter() {
+            game = game.play(m).expect("moves were legal when played");
+        }
+        self.game = game;
+        self.opening = find_eco_from_moves(&self.moves).cloned();
+        // back to the score of the position we're on, if there's one
+        self.score = self.scores.last().cloned().unwrap_or(Score::None);
+        self.undone.push(move_.clone());
+        self.undone_scores.push(score);
+        let turn = self.game.turn();
+        if let Some(clock) = self.clock.as_mut() {
+            clock.switch_to(turn);
+        }
+        Some(move_)
+    }
+
+    /// Play again the last move taken back.
+    pub fn redo(&mut self) -> Option<Move> {
+        let move_ = self.undone.pop()?;
+        let score = self.undone_scores.pop().unwrap_or(Score::None);
+        if self.push_move(move_.clone()) {
+            self.set_score(score);
+            let turn = self.game.turn();
+            if let Some(clock) = self.clock.as_mut() {
+                clock.switch_to(turn);
+            }
+            Some(move_)
+        } else {
+            self.undone.clear();
+            self.undone_scores.clear();
+            None
+        }
+    }
+
+    /// The side whose flag fell
+    pub fn flagged(&self) -> Option<Color> {
+        self.clock.as_ref().and_then(|clock| clock.flagged())
+    }
+
+    /// The outcome of the game, on the board or on the clock.
+    ///
+    /// Running out of time loses, unless the opponent can't mate anyway.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.game.outcome().or_else(|| {
+            self.flagged().map(|color| {
+                if self.game.has_insufficient_material(!color) {
+                    Outcome::Draw
+                } else {
+                    Outcome::Decisive { winner: !color }
+                }
+            })
+        })
+    }
+
+    pub fn fen(&self) -> String {
+        Fen::from_position(self.game.clone(), shakmaty::EnPassantMode::Legal).to_string()
     }
 
     pub fn clear_score(&mut self) {
         self.score = Score::None;
     }
 
+    /// The engine's score of the position we're on
     pub fn set_score(&mut self, score: Score) {
+        if let Some(last) = self.scores.last_mut() {
+            *last = score.clone();
+        }
         self.score = score;
     }
+
+    pub fn set_analysis(&mut self, lines: Vec<SearchInfo>) {
+        self.analysis = lines;
+    }
+
+    /// The game so far, tagged for a PGN export
+    pub fn pgn(&self, players: Players) -> PgnGame {
+        let mut pgn = PgnGame::new(self.start.clone(), self.moves.clone());
+        let (white, black, event) = match players {
+            Players::Humans => (
+                PLAYER_NAME.to_string(),
+                PLAYER_NAME.to_string(),
+                String::from("Casual game"),
+            ),
+            Players::Engine(name) => {
+                let event = format!("Game against {name}");
+                if self.engine_color == Color::White {
+                    (name, PLAYER_NAME.to_string(), event)
+                } else {
+                    (PLAYER_NAME.to_string(), name, event)
+                }
+            }
+            Players::Engines { white, black } => {
+                let event = format!("{white} vs {black}");
+                (white, black, event)
+            }
+        };
+        pgn.set_tag("Event", event);
+        pgn.set_tag("Date", chrono::Local::now().format("%Y.%m.%d").to_string());
+        pgn.set_tag("White", white);
+        pgn.set_tag("Black", black);
+        pgn.set_tag(
+            "Result",
+            self.outcome()
+                .map(|outcome| outcome.to_string())
+                .unwrap_or(String::from("*")),
+        );
+        if let Some(time_control) = get_time_control() {
+            pgn.set_tag(
+                "TimeControl",
+                format!(
+                    "{}+{}",
+                    time_control.base.as_secs_f64(),
+                    time_control.increment.as_secs_f64()
+                ),
+            );
+        }
+        if self.game.outcome().is_none() && self.flagged().is_some() {
+            pgn.set_tag("Termination", "time forfeit");
+        }
+        if let Some(eco) = &self.opening {
+            pgn.set_tag("ECO", eco.code.clone());
+            pgn.set_tag("Opening", eco.name.clone());
+        }
+        pgn
+    }
+}
+
+fn position_from_fen(fen_string: &str) -> Result<Chess, String> {
+    let fen = Fen::from_str(fen_string).map_err(|err| format!("invalid FEN: {err}"))?;
+    Chess::from_setup(fen.into_setup(), shakmaty::CastlingMode::Standard)
+        .map_err(|err| err.to_string())
 }
 
 pub type OpeningItem = (Vec<Move>, String);
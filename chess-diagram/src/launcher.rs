@@ -0,0 +1,58 @@
+use egui::Ui;
+
+use crate::config::get_engine_profiles;
+
+pub enum LauncherAction {
+    /// a profile name or a path
+    Start(String),
+    Cancel,
+}
+
+/// Picks an engine to start, from the profiles of the configuration
+/// file or by its path.
+pub struct EngineLauncher {
+    input: String,
+}
+
+impl EngineLauncher {
+    pub fn new() -> Self {
+        Self {
+            input: String::new(),
+        }
+    }
+
+    pub fn render_panel(&mut self, ui: &mut Ui) -> Option<LauncherAction> {
+        let mut action = None;
+
+        ui.heading("Engine");
+        ui.separator();
+        let profiles = get_engine_profiles();
+        if !profiles.is_empty() {
+            ui.label("Profiles");
+            for profile in profiles {
+                if ui.button(&profile).clicked() {
+                    action = Some(LauncherAction::Start(profile));
+                }
+            }
+            ui.separator();
+        }
+
+        ui.label("Path");
+        let response = ui.text_edit_singleline(&mut self.input);
+        let entered =
+            response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let path = self.input.trim();
+            if (ui.button("Start").clicked() || entered) && !path.is_empty() {
+                action = Some(LauncherAction::Start(path.to_string()));
+            }
+            if ui.button("Cancel").clicked() {
+                action = Some(LauncherAction::Cancel);
+            }
+        });
+
+        action
+    }
+}
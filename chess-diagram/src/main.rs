@@ -1,14 +1,28 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
+mod analysis;
 mod app;
+mod batch;
 mod board;
+mod clock;
 mod config;
+mod engine_match;
+mod eval_bar;
+mod export;
 mod game;
 mod gesture;
+mod launcher;
+mod piece_set;
+mod png;
 mod promotion;
 mod proxy;
+mod settings;
+mod setup;
 mod side;
 mod sources;
+mod strength;
+mod svg;
+mod theme;
 
 static DEFAULT_SIZE: [f32; 2] = [800.0, 600.0];
 
@@ -17,10 +31,23 @@ static DEFAULT_SIZE: [f32; 2] = [800.0, 600.0];
 fn main() -> eframe::Result {
     use app::DiagramApp;
 
-    use crate::config::get_fullscreen;
+    use crate::config::{get_command, get_config_toml, get_fullscreen, get_print_config};
 
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    if get_print_config() {
+        print!("{}", get_config_toml());
+        return Ok(());
+    }
+
+    if let Some(command) = get_command() {
+        if let Err(err) = export::run(command) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size(DEFAULT_SIZE)
@@ -0,0 +1,379 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use image::{
+    DynamicImage, GenericImageView, ImageFormat, ImageReader, RgbaImage,
+    imageops::{self, FilterType},
+};
+use resvg::{tiny_skia, usvg};
+use serde::Deserialize;
+
+use crate::svg::piece_svg;
+
+/// Drawn from the vectors of the Leipzig sprite.
+pub const DEFAULT_PIECE_SET: &str = "leipzig";
+
+/// Sprites of six columns from kings to pawns, black pieces on the first row.
+const BUILTIN_SPRITES: [(&str, &[u8]); 2] = [
+    ("thin", include_bytes!("../assets/pieces2.png")),
+    ("bold", include_bytes!("../assets/pieces-bak.png")),
+];
+const BUILTIN_ORDER: &str = "kqrbnpKQRBNP";
+
+/// Pieces by their FEN character
+const PIECE_NAMES: [&str; 12] = ["K", "Q", "R", "B", "N", "P", "k", "q", "r", "b", "n", "p"];
+
+/// Side of the cells SVG sprites are cut from
+const SPRITE_CELL_SIZE: u32 = 256;
+
+const MANIFEST: &str = "manifest.toml";
+
+/// A sprite sheet described in the manifest of a piece set.
+///
+/// ```toml
+/// sprite = "pieces.png"
+/// columns = 6
+/// rows = 2
+/// order = "KQRBNPkqrbnp"
+/// ```
+#[derive(Deserialize)]
+struct Manifest {
+    sprite: String,
+    columns: u32,
+    rows: u32,
+    /// FEN characters of the cells, row after row
+    order: String,
+}
+
+/// A piece drawn once for all, or from vectors at the size it's shown.
+pub enum PieceImage {
+    Raster(DynamicImage),
+    /// an SVG document
+    Vector(Vec<u8>),
+}
+
+impl PieceImage {
+    /// The piece filling `width` by `height` pixels.
+    pub fn render(&self, width: u32, height: u32) -> RgbaImage {
+        match self {
+            PieceImage::Raster(image) => {
+                imageops::resize(image, width, height, FilterType::Lanczos3)
+            }
+            PieceImage::Vector(bytes) => {
+                rasterize_svg(bytes, width, height).unwrap_or_else(|err| {
+                    log::error!("failed to draw a piece: {err}");
+                    RgbaImage::new(width, height)
+                })
+            }
+        }
+    }
+}
+
+/// Twelve images, one for each piece.
+pub struct PieceSet {
+    pub name: String,
+    pub pieces: Vec<(&'static str, PieceImage)>,
+}
+
+impl PieceSet {
+    /// A built-in set by its name, or a set from a directory.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if name == DEFAULT_PIECE_SET {
+            let pieces = PIECE_NAMES
+                .iter()
+                .filter_map(|name| {
+                    let svg = piece_svg(name.chars().next()?)?;
+                    Some((*name, PieceImage::Vector(svg.into_bytes())))
+                })
+                .collect();
+            return Ok(Self {
+                name: name.to_string(),
+                pieces,
+            });
+        }
+        match BUILTIN_SPRITES.iter().find(|(builtin, _)| *builtin == name) {
+            Some((name, bytes)) => {
+                let image = decode(bytes, Some(ImageFormat::Png))?;
+                Ok(Self {
+                    name: name.to_string(),
+                    pieces: sprite_pieces(&image, 6, 2, BUILTIN_ORDER)?,
+                })
+            }
+            None => Self::from_dir(Path::new(name)),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        Self::load(DEFAULT_PIECE_SET).expect("built-in pieces should load")
+    }
+
+    /// Either a sprite along with its manifest, or a file for each piece,
+    /// named "wK.svg" or "bN.png" as in most sets around.
+    fn from_dir(dir: &Path) -> Result<Self, String> {
+        if !dir.is_dir() {
+            return Err(format!(
+                "unknown piece set '{}', not one of {} nor a directory",
+                dir.display(),
+                builtin_names().join(", ")
+            ));
+        }
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| dir.display().to_string());
+
+        let manifest_path = dir.join(MANIFEST);
+        let pieces = if manifest_path.is_file() {
+            let content = fs::read_to_string(&manifest_path)
+                .map_err(|err| format!("failed to read {}: {err}", manifest_path.display()))?;
+            let manifest: Manifest = toml::from_str(&content)
+                .map_err(|err| format!("invalid {}: {err}", manifest_path.display()))?;
+            let sprite_path = dir.join(&manifest.sprite);
+            let image = if is_svg(&sprite_path) {
+                let bytes = read(&sprite_path)?;
+                let (width, height) = (
+                    manifest.columns * SPRITE_CELL_SIZE,
+                    manifest.rows * SPRITE_CELL_SIZE,
+                );
+                rasterize_svg(&bytes, width, height)
+                    .map(DynamicImage::ImageRgba8)
+                    .map_err(|err| format!("failed to load {}: {err}", sprite_path.display()))?
+            } else {
+                load_bitmap(&sprite_path)?
+            };
+            sprite_pieces(&image, manifest.columns, manifest.rows, &manifest.order)
+                .map_err(|err| format!("{}: {err}", manifest_path.display()))?
+        } else {
+            PIECE_NAMES
+                .iter()
+                .map(|name| {
+                    let path = piece_file(dir, name)?;
+                    Ok((*name, load_piece(&path)?))
+                })
+                .collect::<Result<Vec<_>, String>>()?
+        };
+        Ok(Self { name, pieces })
+    }
+}
+
+pub fn builtin_names() -> Vec<&'static str> {
+    let mut names = vec![DEFAULT_PIECE_SET];
+    names.extend(BUILTIN_SPRITES.iter().map(|(name, _)| *name));
+    names
+}
+
+fn piece_file(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let color = if name.chars().all(|c| c.is_ascii_uppercase()) {
+        'w'
+    } else {
+        'b'
+    };
+    let stem = format!("{color}{}", name.to_ascii_uppercase());
+    ["svg", "png"]
+        .iter()
+        .map(|extension| dir.join(format!("{stem}.{extension}")))
+        .find(|path| path.is_file())
+        .ok_or(format!("no {stem}.svg nor {stem}.png in {}", dir.display()))
+}
+
+fn decode(bytes: &[u8], format: Option<ImageFormat>) -> Result<DynamicImage, String> {
+    let reader = match format {
+        Some(format) => ImageReader::with_format(Cursor::new(bytes), format),
+        None => ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|err| err.to_string())?,
+    };
+    reader.decode().map_err(|err| err.to_string())
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "svg")
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("failed to read {}: {err}", path.display()))
+}
+
+fn load_bitmap(path: &Path) -> Result<DynamicImage, String> {
+    decode(&read(path)?, None).map_err(|err| format!("failed to load {}: {err}", path.display()))
+}
+
+/// A piece from its own file, SVGs being checked here rather than when drawn.
+fn load_piece(path: &Path) -> Result<PieceImage, String> {
+    if is_svg(path) {
+        let bytes = read(path)?;
+        usvg::Tree::from_data(&bytes, &usvg::Options::default())
+            .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+        Ok(PieceImage::Vector(bytes))
+    } else {
+        Ok(PieceImage::Raster(square(load_bitmap(path)?)))
+    }
+}
+
+/// Draw an SVG in the middle of `width` by `height` pixels, keeping its proportions.
+fn rasterize_svg(bytes: &[u8], width: u32, height: u32) -> Result<RgbaImage, String> {
+    let tree =
+        usvg::Tree::from_data(bytes, &usvg::Options::default()).map_err(|err| err.to_string())?;
+    let size = tree.size();
+    let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (width as f32 - size.width() * scale) / 2.0,
+        (height as f32 - size.height() * scale) / 2.0,
+    );
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("failed to allocate a pixmap")?;
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels).ok_or(String::from("failed to convert the pixmap"))
+}
+
+/// Pad an image to a square, for pieces not to be stretched on their squares.
+fn square(image: DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width == height {
+        return image;
+    }
+    let side = width.max(height);
+    let mut padded = RgbaImage::new(side, side);
+    imageops::overlay(
+        &mut padded,
+        &image,
+        ((side - width) / 2) as i64,
+        ((side - height) / 2) as i64,
+    );
+    DynamicImage::ImageRgba8(padded)
+}
+
+/// Make transparent the white around a piece, as some sprites come on
+/// an opaque background.
+fn clear_background(image: &mut RgbaImage) {
+    let (width, height) = image.dimensions();
+    let is_background =
+        |pixel: &image::Rgba<u8>| pixel[3] > 0 && pixel.0[..3].iter().all(|c| *c > 230);
+    let mut stack: Vec<(u32, u32)> = (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]))
+        .collect();
+    while let Some((x, y)) = stack.pop() {
+        let pixel = image.get_pixel_mut(x, y);
+        if !is_background(pixel) {
+            continue;
+        }
+        pixel[3] = 0;
+        if x > 0 {
+            stack.push((x - 1, y));
+        }
+        if y > 0 {
+            stack.push((x, y - 1));
+        }
+        if x + 1 < width {
+            stack.push((x + 1, y));
+        }
+        if y + 1 < height {
+            stack.push((x, y + 1));
+        }
+    }
+}
+
+/// Bounds of what's drawn in an image, as (left, top, right, bottom).
+///
+/// Rows and columns with only a few pixels don't count, for specks
+/// left over from cutting a sprite not to get in the way.
+fn drawn_bounds(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let (width, height) = image.dimensions();
+    let drawn = |x: u32, y: u32| image.get_pixel(x, y)[3] > 16;
+    let rows: Vec<u32> = (0..height)
+        .filter(|y| (0..width).filter(|x| drawn(*x, *y)).count() as u32 > width / 32)
+        .collect();
+    let columns: Vec<u32> = (0..width)
+        .filter(|x| (0..height).filter(|y| drawn(*x, *y)).count() as u32 > height / 32)
+        .collect();
+    Some((
+        *columns.first()?,
+        *rows.first()?,
+        *columns.last()? + 1,
+        *rows.last()? + 1,
+    ))
+}
+
+/// Put the pieces of a sprite on squares of the same size, the largest
+/// one taking most of it, all of them standing on the same line.
+fn fit_pieces(cells: Vec<(&'static str, RgbaImage)>) -> Vec<(&'static str, PieceImage)> {
+    let bounds: Vec<_> = cells.iter().map(|(_, cell)| drawn_bounds(cell)).collect();
+    let largest = bounds
+        .iter()
+        .flatten()
+        .map(|(left, top, right, bottom)| (right - left).max(bottom - top))
+        .max()
+        .unwrap_or(1);
+    let side = (largest as f32 / 0.85).ceil() as u32;
+    let baseline = (side as f32 * 0.95) as i64;
+
+    cells
+        .into_iter()
+        .zip(bounds)
+        .map(|((name, cell), bounds)| {
+            let mut fitted = RgbaImage::new(side, side);
+            let (x, y) = match bounds {
+                Some((left, _, right, bottom)) => (
+                    (side as i64 - (right - left) as i64) / 2 - left as i64,
+                    baseline - bottom as i64,
+                ),
+                None => (
+                    (side as i64 - cell.width() as i64) / 2,
+                    (side as i64 - cell.height() as i64) / 2,
+                ),
+            };
+            imageops::overlay(&mut fitted, &cell, x, y);
+            (name, PieceImage::Raster(DynamicImage::ImageRgba8(fitted)))
+        })
+        .collect()
+}
+
+/// Cut a sprite sheet in cells, `order` giving the piece in each of them.
+fn sprite_pieces(
+    image: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    order: &str,
+) -> Result<Vec<(&'static str, PieceImage)>, String> {
+    if columns == 0 || rows == 0 {
+        return Err(String::from("a sprite needs columns and rows"));
+    }
+    let cell_width = image.width() / columns;
+    let cell_height = image.height() / rows;
+    let cells = PIECE_NAMES
+        .iter()
+        .map(|name| {
+            let index = order
+                .chars()
+                .position(|c| name.starts_with(c))
+                .ok_or(format!("piece {name} missing from '{order}'"))?
+                as u32;
+            if index >= columns * rows {
+                return Err(format!("piece {name} out of the sprite"));
+            }
+            let mut cell = image
+                .crop_imm(
+                    (index % columns) * cell_width,
+                    (index / columns) * cell_height,
+                    cell_width,
+                    cell_height,
+                )
+                .to_rgba8();
+            clear_background(&mut cell);
+            Ok((*name, cell))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(fit_pieces(cells))
+}
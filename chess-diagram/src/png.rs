@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font as _, FontRef, PxScale, ScaleFont, point};
+use egui::{Align2, Color32, Pos2, Rect, pos2, vec2};
+use image::{
+    Rgba, RgbaImage,
+    imageops::{self, FilterType},
+};
+use shakmaty::{Color, Move, Setup};
+
+use crate::{
+    board::{FRAME_GAP, MARGIN, board_rect, coordinate_labels, square_from_indices},
+    piece_set::PieceSet,
+    sources::dark_square_image,
+    theme::{Font, SquareFill, Theme},
+};
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+fn rgba(color: Color32) -> Rgba<u8> {
+    Rgba(color.to_srgba_unmultiplied())
+}
+
+/// The fonts egui has for these families.
+fn font_ref(font: Font) -> FontRef<'static> {
+    let data = match font {
+        Font::Proportional => epaint_default_fonts::UBUNTU_LIGHT,
+        Font::Monospace => epaint_default_fonts::HACK_REGULAR,
+    };
+    FontRef::try_from_slice(data).expect("the default fonts should load")
+}
+
+/// Blend `color` over a pixel, `coverage` being the part of the pixel it covers.
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = color[3] as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for channel in 0..3 {
+        pixel[channel] =
+            (pixel[channel] as f32 * (1.0 - alpha) + color[channel] as f32 * alpha).round() as u8;
+    }
+}
+
+fn fill_rect(image: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    for y in rect.min.y.round() as i64..rect.max.y.round() as i64 {
+        for x in rect.min.x.round() as i64..rect.max.x.round() as i64 {
+            blend(image, x, y, color, 1.0);
+        }
+    }
+}
+
+fn fill_circle(image: &mut RgbaImage, center: Pos2, radius: f32, color: Rgba<u8>) {
+    let rect = Rect::from_center_size(center, vec2(radius * 2.0, radius * 2.0)).expand(1.0);
+    for y in rect.min.y.floor() as i64..rect.max.y.ceil() as i64 {
+        for x in rect.min.x.floor() as i64..rect.max.x.ceil() as i64 {
+            let distance = center.distance(pos2(x as f32 + 0.5, y as f32 + 0.5));
+            blend(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+/// Like `Painter::text`, with the fonts egui uses.
+fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontRef<'_>,
+    text: &str,
+    size: f32,
+    pos: Pos2,
+    align: Align2,
+    color: Rgba<u8>,
+) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        glyphs.push((id, caret));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+    }
+
+    let height = scaled.ascent() - scaled.descent() + scaled.line_gap();
+    let rect = align.anchor_size(pos, vec2(caret, height));
+    let baseline = rect.min.y + scaled.ascent();
+    for (id, x) in glyphs {
+        let glyph = id.with_scale_and_position(scale, point(rect.min.x + x, baseline));
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, coverage| {
+                blend(
+                    image,
+                    bounds.min.x as i64 + x as i64,
+                    bounds.min.y as i64 + y as i64,
+                    color,
+                    coverage,
+                );
+            });
+        }
+    }
+}
+
+/// Render a position as an image of `size` pixels, laid out as
+/// `render_board` does it on screen.
+pub fn render_png(
+    position: &Setup,
+    piece_set: &PieceSet,
+    theme: &Theme,
+    last_move: Option<&Move>,
+    title: Option<&str>,
+    orientation: Color,
+    size: u32,
+) -> RgbaImage {
+    let size = size.max((2.0 * MARGIN) as u32 + 64);
+    let rect = Rect::from_min_size(Pos2::ZERO, vec2(size as f32, size as f32));
+    let board_rect = board_rect(&rect);
+    let square_size = board_rect.width() / 8.0;
+    let font = font_ref(theme.coordinates.font);
+    let background = rgba(theme.background);
+    let text_color = rgba(theme.coordinates.color);
+
+    // backround
+    let mut image = RgbaImage::from_pixel(size, size, background);
+
+    // title
+    if let Some(title) = title {
+        let pos = pos2(
+            board_rect.min.x + board_rect.width() / 2.0,
+            rect.min.y + MARGIN / 3.0,
+        );
+        draw_text(
+            &mut image,
+            &font,
+            title,
+            16.0,
+            pos,
+            Align2::CENTER_TOP,
+            text_color,
+        );
+    }
+
+    // frame
+    if theme.frame.width > 0.0 {
+        let frame_rect = board_rect.expand(FRAME_GAP + theme.frame.width);
+        fill_rect(&mut image, frame_rect, rgba(theme.frame.color));
+        fill_rect(&mut image, board_rect.expand(FRAME_GAP), background);
+    }
+
+    // turn
+    let top = if position.turn == orientation {
+        board_rect.max.y - MARGIN / 3.0
+    } else {
+        board_rect.min.y + MARGIN / 3.0
+    };
+    let center = pos2(board_rect.max.x + MARGIN * 0.7, top);
+    if position.turn == Color::Black {
+        fill_circle(&mut image, center, 12.0, BLACK);
+    } else {
+        fill_circle(&mut image, center, 10.5, BLACK);
+        fill_circle(&mut image, center, 7.5, WHITE);
+    }
+
+    let hatching = dark_square_image();
+    let texture = |fill: &SquareFill| match fill {
+        SquareFill::Hatching => hatching.clone(),
+        SquareFill::Texture(image) => Some(image.clone()),
+        SquareFill::Color(_) => None,
+    };
+    let (light_square, dark_square) = (texture(&theme.light), texture(&theme.dark));
+    let pieces: HashMap<&str, _> = piece_set
+        .pieces
+        .iter()
+        .map(|(name, piece)| (*name, piece))
+        .collect();
+    // squares are a pixel apart in size, scaled images are kept by size
+    let mut scaled: HashMap<(&str, u32, u32), RgbaImage> = HashMap::new();
+    let mut scaled_image = |name: &'static str, width: u32, height: u32| {
+        scaled
+            .entry((name, width, height))
+            .or_insert_with(|| {
+                if let Some(texture) = match name {
+                    "light-square" => light_square.as_ref(),
+                    "dark-square" => dark_square.as_ref(),
+                    _ => None,
+                } {
+                    imageops::resize(texture, width, height, FilterType::Lanczos3)
+                } else {
+                    pieces
+                        .get(name)
+                        .map(|piece| piece.render(width, height))
+                        .unwrap_or_default()
+                }
+            })
+            .clone()
+    };
+
+    let last_move_from = last_move.and_then(|move_| move_.from());
+    let last_move_to = last_move.map(ucui_utils::move_classic_to);
+    let edge = |index: usize, min: f32| (min + index as f32 * square_size).round();
+
+    for rank_index in 0..8usize {
+        for file_index in 0..8usize {
+            let top = edge(rank_index, board_rect.min.y);
+            let left = edge(file_index, board_rect.min.x);
+            let square_rect = Rect::from_min_max(
+                pos2(left, top),
+                pos2(
+                    edge(file_index + 1, board_rect.min.x),
+                    edge(rank_index + 1, board_rect.min.y),
+                ),
+            );
+            let (width, height) = (square_rect.width() as u32, square_rect.height() as u32);
+            let square = square_from_indices(rank_index, file_index, orientation);
+
+            let (fill, name) = if square.is_dark() {
+                (&theme.dark, "dark-square")
+            } else {
+                (&theme.light, "light-square")
+            };
+            match fill {
+                SquareFill::Color(color) => fill_rect(&mut image, square_rect, rgba(*color)),
+                SquareFill::Hatching | SquareFill::Texture(_) => {
+                    let texture = scaled_image(name, width, height);
+                    imageops::overlay(&mut image, &texture, left as i64, top as i64);
+                }
+            }
+
+            if last_move_from == Some(square) || last_move_to == Some(square) {
+                fill_rect(&mut image, square_rect, rgba(theme.highlights.last_move));
+            }
+
+            if let Some(piece) = position.board.piece_at(square)
+                && let Some(name) = piece_name(piece.char())
+            {
+                let piece_image = scaled_image(name, width, height);
+                imageops::overlay(&mut image, &piece_image, left as i64, top as i64);
+            }
+        }
+    }
+
+    // coordinates
+    for label in coordinate_labels(
+        &board_rect,
+        orientation,
+        theme.coordinates.placement,
+        last_move_to,
+    ) {
+        draw_text(
+            &mut image,
+            &font,
+            &label.text,
+            theme.coordinates.size,
+            label.pos,
+            label.align,
+            text_color,
+        );
+    }
+
+    image
+}
+
+fn piece_name(piece_char: char) -> Option<&'static str> {
+    ["K", "Q", "R", "B", "N", "P", "k", "q", "r", "b", "n", "p"]
+        .into_iter()
+        .find(|name| name.starts_with(piece_char))
+}
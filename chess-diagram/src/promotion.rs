@@ -2,12 +2,18 @@ use egui::{Color32, Context, CornerRadius, Rect, StrokeKind, Ui, pos2, vec2};
 use shakmaty::{Color, Role};
 
 use crate::{
-    board::{board_rect, file_to_index, rank_to_index},
+    board::{board_rect, square_to_indices},
     gesture::Gesture,
     sources::Sources,
 };
 
-pub fn render_promotion(ctx: &Context, ui: &mut Ui, sources: &Sources<'_>, gesture: &mut Gesture) {
+pub fn render_promotion(
+    ctx: &Context,
+    ui: &mut Ui,
+    sources: &Sources<'_>,
+    gesture: &mut Gesture,
+    orientation: Color,
+) {
     use Role::*;
 
     if let Gesture::End(state) = *gesture {
@@ -17,12 +23,12 @@ pub fn render_promotion(ctx: &Context, ui: &mut Ui, sources: &Sources<'_>, gestu
         let board_rect = board_rect(&rect);
         let square_size = board_rect.width() / 8.0;
 
-        let rank_index = rank_to_index(state.to().rank());
-        let file_index = file_to_index(state.to().file());
+        let (rank_index, file_index) = square_to_indices(state.to(), orientation);
 
         let left = file_index as f32 * square_size + board_rect.min.x;
 
-        let top = if state.piece().color == Color::Black {
+        // pops up towards the center of the board
+        let top = if rank_index == 7 {
             rank_index as f32 * square_size + board_rect.min.y - (3.0 * square_size)
         } else {
             rank_index as f32 * square_size + board_rect.min.y
@@ -63,15 +69,16 @@ pub fn render_promotion(ctx: &Context, ui: &mut Ui, sources: &Sources<'_>, gestu
 
         ui.input(|input| {
             if input.pointer.primary_clicked()
-                && let Some(position) = input.pointer.interact_pos() {
-                    for (rect, role) in buttons {
-                        if rect.contains(position) {
-                            log::info!("=> {} {}  -> {:?}", rect, position, role);
-                            gesture.promote(role);
-                            return;
-                        }
+                && let Some(position) = input.pointer.interact_pos()
+            {
+                for (rect, role) in buttons {
+                    if rect.contains(position) {
+                        log::info!("=> {} {}  -> {:?}", rect, position, role);
+                        gesture.promote(role);
+                        return;
                     }
                 }
+            }
         })
     }
 
@@ -1,6 +1,7 @@
 use std::{
     sync::{
         Arc, Mutex, RwLock,
+        atomic::{AtomicU16, AtomicU64, Ordering},
         mpsc::{Sender, channel},
     },
     thread::spawn,
@@ -8,75 +9,265 @@ use std::{
 
 use egui::Context;
 use shakmaty::Move;
-use ucui_engine::{EngineCommand, EngineMessage, connect_engine};
+use ucui_engine::{
+    Engine, EngineCommand, EngineError, EngineMessage, EngineOption, SearchId, SearchLimits,
+    Strength, connect_engine, find_option,
+};
 
 use crate::{
-    config::{get_engine, get_engine_args, get_engine_depth, get_engine_options},
+    config::{EngineSetup, get_multi_pv},
     game::GameState,
 };
 
 pub struct Proxy {
     tx: Sender<EngineCommand>,
+    name: Arc<RwLock<String>>,
+    /// declared by the engine, once it's started
+    options: Arc<RwLock<Vec<EngineOption>>>,
+    /// how it was started, to start it again
+    setup: EngineSetup,
+    /// the last failure, until the app picks it up
+    error: Arc<Mutex<Option<EngineError>>>,
+    /// the search whose replies are expected, the others being stale
+    search: Arc<AtomicU64>,
+    /// lines of the analysis, from the command line then the options panel
+    multi_pv: AtomicU16,
 }
 
 impl Proxy {
-    fn new(tx: Sender<EngineCommand>) -> Self {
-        Proxy { tx }
+    fn new(
+        tx: Sender<EngineCommand>,
+        name: Arc<RwLock<String>>,
+        options: Arc<RwLock<Vec<EngineOption>>>,
+        setup: EngineSetup,
+        error: Arc<Mutex<Option<EngineError>>>,
+        search: Arc<AtomicU64>,
+    ) -> Self {
+        Proxy {
+            tx,
+            name,
+            options,
+            setup,
+            error,
+            search,
+            multi_pv: AtomicU16::new(get_multi_pv()),
+        }
+    }
+
+    fn send(&self, command: EngineCommand) {
+        if self.tx.send(command).is_err() {
+            log::error!("Engine is gone, command dropped");
+        }
+    }
+
+    pub fn setup(&self) -> &EngineSetup {
+        &self.setup
+    }
+
+    /// What went wrong with the engine since last asked
+    pub fn take_error(&self) -> Option<EngineError> {
+        self.error.lock().ok().and_then(|mut error| error.take())
     }
 
-    // fn name(&self) -> String {
-    //     self.inner.name()
-    // }
+    pub fn name(&self) -> String {
+        self.name
+            .read()
+            .map(|name| name.clone())
+            .unwrap_or(String::from("-"))
+    }
+
+    pub fn options(&self) -> Vec<EngineOption> {
+        self.options
+            .read()
+            .map(|options| options.clone())
+            .unwrap_or_default()
+    }
 
+    /// Values are checked against the declared options by the engine
+    pub fn set_option(&self, name: String, value: Option<String>) {
+        if name.eq_ignore_ascii_case("MultiPV")
+            && let Some(option) = find_option(&self.options(), &name)
+            && option.validate(value.as_deref()).is_ok()
+            && let Some(multi_pv) = value.as_deref().and_then(|value| value.trim().parse().ok())
+        {
+            self.multi_pv.store(multi_pv, Ordering::SeqCst);
+        }
+        self.send(EngineCommand::SetOption { name, value });
+    }
+
+    /// For the moves to come, analysis going on at full strength
+    pub fn set_strength(&self, strength: Option<Strength>) {
+        self.send(EngineCommand::SetStrength { strength });
+    }
+
+    /// A new search, replies to the earlier ones being dropped from now on
+    fn next_search(&self) -> SearchId {
+        self.search.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Called with the game state locked, for a move of the previous
+    /// game not to be played in the new one.
     pub fn new_game(&self) {
-        self.tx
-            .send(EngineCommand::NewGame)
-            .expect("Err proxy new_game ");
+        self.next_search();
+        self.send(EngineCommand::NewGame);
     }
 
+    /// Interrupt the search, if any. Called with the game state locked
+    /// before taking back moves, a reply on its way is dropped.
     pub fn stop(&self) {
-        self.tx.send(EngineCommand::Stop).expect("Err proxy stop ");
+        self.next_search();
+        self.send(EngineCommand::Stop);
+    }
+
+    /// Whether the reply to this search is still expected
+    pub fn is_searching(&self, id: SearchId) -> bool {
+        self.search.load(Ordering::SeqCst) == id
+    }
+
+    /// Ask for a move in the current position, with the clocks if the game is timed
+    pub fn play(&self, game_state: &GameState) -> SearchId {
+        let mut limits = self.setup.limits.clone();
+        // the moves to search that can be played here, if any
+        limits
+            .searchmoves
+            .retain(|move_| move_.to_move(&game_state.game).is_ok());
+        if let Some(clock) = &game_state.clock {
+            // on the clock, the engine manages its time rather than going to depth
+            limits.time = Some(clock.time());
+            limits.depth = None;
+        }
+        self.search(game_state.fen(), limits)
+    }
+
+    /// Ask for a move in `fen`, searching within `limits`. An infinite or
+    /// pondering search is stopped for its move after `movetime`, and goes
+    /// on until stopped without it.
+    pub fn search(&self, fen: String, limits: SearchLimits) -> SearchId {
+        let id = self.next_search();
+        self.send(EngineCommand::Go { id, fen, limits });
+        id
     }
 
-    pub fn play(&self, fen: String) {
-        self.tx
-            .send(EngineCommand::Go {
-                fen,
-                depth: get_engine_depth(),
-            })
-            .expect("Err proxy play ");
+    /// Start analysing the position, until stopped or asked for another one
+    pub fn analyse(&self, fen: String) {
+        self.send(EngineCommand::Analyse {
+            id: self.next_search(),
+            fen,
+            multi_pv: self.multi_pv.load(Ordering::SeqCst),
+        });
     }
 }
 
-pub fn start_engine(state: Arc<RwLock<GameState>>, ctx: Arc<Mutex<Context>>) -> Proxy {
+pub fn start_engine(
+    setup: EngineSetup,
+    state: Arc<RwLock<GameState>>,
+    ctx: Arc<Mutex<Context>>,
+) -> Proxy {
     let (tx, rx) = channel::<EngineCommand>();
+    let name = Arc::new(RwLock::new(String::from("-")));
+    let engine_name = name.clone();
+    let options = Arc::new(RwLock::new(Vec::new()));
+    let engine_options = options.clone();
+    let proxy_setup = setup.clone();
+    let error = Arc::new(Mutex::new(None));
+    let engine_error = error.clone();
+    let search = Arc::new(AtomicU64::new(0));
+    let expected = search.clone();
     let _ = spawn(move || {
-        let engine = connect_engine(&get_engine(), get_engine_args(), get_engine_options());
-        loop {
-            if let Ok(command) = rx.recv() {
-                match command {
-                    EngineCommand::NewGame => engine.new_game(),
-                    EngineCommand::Stop => engine.stop(),
-                    EngineCommand::Go { fen, depth } => {
-                        engine.go(fen, depth);
-
-                        if let Ok(EngineMessage::BestMove { move_, score }) = engine.recv() {
-                            let move_: Move = move_.into();
-                            log::info!("Engine played {move_}");
-                            let mut state =
-                                state.write().expect("failed to get a writable game state");
-                            state.make_move(move_);
-                            state.set_score(score);
-
-                            if let Ok(ctx) = ctx.lock() {
-                                ctx.request_repaint();
-                            }
-                        }
-                    }
+        let engine: Arc<Box<dyn Engine + Send + Sync>> =
+            match connect_engine(&setup.path, setup.args, setup.options) {
+                Ok(engine) => Arc::new(engine),
+                Err(err) => {
+                    log::error!("Engine {} not started: {err}", setup.path);
+                    report_error(err, &engine_error, &ctx);
+                    return;
+                }
+            };
+        if let Ok(mut name) = engine_name.write() {
+            *name = engine.name();
+        }
+        if let Ok(mut options) = engine_options.write() {
+            *options = engine.options();
+        }
+
+        // engine messages are handled on their own, for commands not to wait on a search
+        let receiver = engine.clone();
+        let _ = spawn(move || {
+            while let Ok(message) = receiver.recv() {
+                match message {
+                    EngineMessage::Error(err) => report_error(err, &engine_error, &ctx),
+                    message => handle_message(message, &expected, &state, &ctx),
                 }
             }
+        });
+
+        while let Ok(command) = rx.recv() {
+            match command {
+                EngineCommand::NewGame => engine.new_game(),
+                EngineCommand::Stop => engine.stop(),
+                EngineCommand::Go { id, fen, limits } => engine.go(id, fen, limits),
+                EngineCommand::Analyse { id, fen, multi_pv } => engine.analyse(id, fen, multi_pv),
+                EngineCommand::SetOption { name, value } => engine.set_option(name, value),
+                EngineCommand::SetStrength { strength } => engine.set_strength(strength),
+            }
         }
+        // the proxy is dropped, for the engine to go with it
+        engine.quit();
     });
 
-    Proxy::new(tx)
+    Proxy::new(tx, name, options, proxy_setup, error, search)
+}
+
+fn report_error(err: EngineError, error: &Mutex<Option<EngineError>>, ctx: &Mutex<Context>) {
+    if let Ok(mut error) = error.lock() {
+        *error = Some(err);
+    }
+    if let Ok(ctx) = ctx.lock() {
+        ctx.request_repaint();
+    }
+}
+
+fn handle_message(
+    message: EngineMessage,
+    expected: &AtomicU64,
+    state: &RwLock<GameState>,
+    ctx: &Mutex<Context>,
+) {
+    let mut state = state.write().expect("failed to get a writable game state");
+    // checked with the state locked, as the search is replaced with it locked
+    let expected = expected.load(Ordering::SeqCst);
+    match message {
+        EngineMessage::BestMove {
+            id,
+            fen,
+            move_,
+            score,
+        } => {
+            let move_: Move = move_.into();
+            if id != expected {
+                log::info!("Engine move {move_} discarded, search {id} was cancelled");
+                return;
+            }
+            // the position might have changed while the engine was thinking
+            // without it being told, in which case this move is cancelled.
+            if state.fen() != fen {
+                log::info!("Engine move {move_} discarded, position changed");
+                return;
+            }
+            log::info!("Engine played {move_}");
+            state.make_move(move_);
+            state.set_score(score);
+        }
+        EngineMessage::Info { id, fen, lines } => {
+            if id != expected || state.fen() != fen {
+                return;
+            }
+            state.set_analysis(lines);
+        }
+        EngineMessage::Id { .. } | EngineMessage::Error(_) => return,
+    }
+
+    if let Ok(ctx) = ctx.lock() {
+        ctx.request_repaint();
+    }
 }
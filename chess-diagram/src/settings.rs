@@ -0,0 +1,135 @@
+use egui::{Color32, ComboBox, Key, Response, ScrollArea, TextEdit, Ui};
+use ucui_engine::{EngineOption, OptionKind};
+
+pub enum SettingsAction {
+    /// an option to send to the engine, checked against its declaration
+    Set(String, Option<String>),
+    Close,
+}
+
+/// The options the engine declares, edited live.
+pub struct EngineSettings {
+    options: Vec<EngineOption>,
+    /// as `setoption` takes them, one for each option
+    values: Vec<String>,
+    errors: Vec<Option<String>>,
+}
+
+impl EngineSettings {
+    /// Values start from the defaults, then the options the engine was started with.
+    pub fn new(options: Vec<EngineOption>, configured: &[(String, Option<String>)]) -> Self {
+        let mut values: Vec<String> = options
+            .iter()
+            .map(|option| option.default_value().unwrap_or_default())
+            .collect();
+        for (name, value) in configured {
+            // names are case insensitive
+            if let Some(index) = options
+                .iter()
+                .position(|option| option.name.eq_ignore_ascii_case(name))
+                && let Some(value) = value
+            {
+                values[index] = value.clone();
+            }
+        }
+        let errors = vec![None; options.len()];
+        Self {
+            options,
+            values,
+            errors,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    pub fn render_panel(&mut self, ui: &mut Ui) -> Option<SettingsAction> {
+        let mut action = None;
+
+        ui.heading("Engine options");
+        ui.separator();
+        if self.options.is_empty() {
+            ui.label("No options");
+        }
+        ScrollArea::vertical()
+            .max_height(ui.available_height() - 40.0)
+            .show(ui, |ui| {
+                for (index, option) in self.options.iter().enumerate() {
+                    let value = &mut self.values[index];
+                    let error = &mut self.errors[index];
+                    let name = option.name.clone();
+                    match &option.kind {
+                        OptionKind::Check { .. } => {
+                            let mut checked = value == "true";
+                            if ui.checkbox(&mut checked, &option.name).changed() {
+                                *value = checked.to_string();
+                                action = Some(SettingsAction::Set(name, Some(value.clone())));
+                            }
+                        }
+                        OptionKind::Combo { vars, .. } => {
+                            ui.label(&option.name);
+                            let selected = value.clone();
+                            ComboBox::from_id_salt(("engine_option", index))
+                                .selected_text(&selected)
+                                .show_ui(ui, |ui| {
+                                    for var in vars {
+                                        if ui.selectable_value(value, var.clone(), var).changed() {
+                                            action = Some(SettingsAction::Set(
+                                                name.clone(),
+                                                Some(var.clone()),
+                                            ));
+                                        }
+                                    }
+                                });
+                        }
+                        OptionKind::Spin { min, max, .. } => {
+                            let range = match (min, max) {
+                                (Some(min), Some(max)) => format!("{min} to {max}"),
+                                (Some(min), None) => format!("from {min}"),
+                                (None, Some(max)) => format!("up to {max}"),
+                                (None, None) => String::from("a number"),
+                            };
+                            ui.label(format!("{} ({range})", option.name));
+                            let response = ui.add(TextEdit::singleline(value).desired_width(80.0));
+                            if response.changed() {
+                                *error = option.validate(Some(value.trim())).err();
+                            }
+                            if entered(ui, &response) && error.is_none() {
+                                action =
+                                    Some(SettingsAction::Set(name, Some(value.trim().to_string())));
+                            }
+                        }
+                        OptionKind::String { .. } => {
+                            ui.label(&option.name);
+                            let response = ui.text_edit_singleline(value);
+                            if entered(ui, &response) {
+                                action = Some(SettingsAction::Set(name, Some(value.clone())));
+                            }
+                        }
+                        OptionKind::Button => {
+                            if ui.button(&option.name).clicked() {
+                                action = Some(SettingsAction::Set(name, None));
+                            }
+                        }
+                    }
+                    if let Some(error) = error {
+                        ui.colored_label(Color32::DARK_RED, error.as_str());
+                    }
+                    ui.add_space(4.0);
+                }
+            });
+
+        ui.separator();
+        if ui.button("Close").clicked() {
+            action = Some(SettingsAction::Close);
+        }
+
+        action
+    }
+}
+
+/// Values typed in go to the engine with Enter
+fn entered(ui: &Ui, response: &Response) -> bool {
+    response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter))
+}
@@ -0,0 +1,176 @@
+use egui::{Color32, ComboBox, Context, PointerButton, Rect, Sense, Ui, vec2};
+use shakmaty::{Chess, Color, EnPassantMode, File, Piece, Position, Rank, Setup, Square, fen::Fen};
+
+use crate::{
+    board::{board_rect, square_at},
+    sources::Sources,
+};
+
+const PALETTE_SQUARE: f32 = 48.0;
+
+const CASTLING_RIGHTS: [(&str, Square); 4] = [
+    ("White O-O", Square::H1),
+    ("White O-O-O", Square::A1),
+    ("Black O-O", Square::H8),
+    ("Black O-O-O", Square::A8),
+];
+
+/// A position being edited.
+///
+/// Pieces are dragged from the palette onto the board, and off the board
+/// to remove them. It's not checked for legality before leaving the setup.
+pub struct SetupState {
+    pub setup: Setup,
+    dragged: Option<Piece>,
+    pub error: Option<String>,
+}
+
+impl SetupState {
+    pub fn from_position(game: &Chess) -> Self {
+        Self {
+            setup: game.clone().into_setup(EnPassantMode::Legal),
+            dragged: None,
+            error: None,
+        }
+    }
+
+    pub fn fen(&self) -> String {
+        Fen::from_setup(self.setup.clone()).to_string()
+    }
+
+    /// Render the palette and the position parameters, returns true
+    /// when the user is done with it.
+    pub fn render_panel(&mut self, ui: &mut Ui, sources: &Sources<'_>) -> bool {
+        let mut done = false;
+
+        ui.heading("Setup");
+        ui.separator();
+        egui::Grid::new("setup_palette").show(ui, |ui| {
+            for role in ['K', 'Q', 'R', 'B', 'N', 'P'] {
+                for name in [role, role.to_ascii_lowercase()] {
+                    let (rect, response) =
+                        ui.allocate_exact_size(vec2(PALETTE_SQUARE, PALETTE_SQUARE), Sense::drag());
+                    if let Some(image) = sources.get(name.to_string()) {
+                        image.paint_at(ui, rect);
+                    }
+                    if response.drag_started() {
+                        self.dragged = Piece::from_char(name);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.label("Side to move");
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.setup.turn, Color::White, "White");
+            ui.radio_value(&mut self.setup.turn, Color::Black, "Black");
+        });
+
+        ui.separator();
+        ui.label("Castling");
+        for (label, rook) in CASTLING_RIGHTS {
+            let mut allowed = self.setup.castling_rights.contains(rook);
+            if ui.checkbox(&mut allowed, label).changed() {
+                self.setup.castling_rights.set(rook, allowed);
+            }
+        }
+
+        ui.separator();
+        ui.label("En passant");
+        // the target square is behind a pawn that just moved, which depends on the side to move
+        let ep_rank = if self.setup.turn == Color::White {
+            Rank::Sixth
+        } else {
+            Rank::Third
+        };
+        if self
+            .setup
+            .ep_square
+            .is_some_and(|square| square.rank() != ep_rank)
+        {
+            self.setup.ep_square = None;
+        }
+        ComboBox::from_id_salt("setup_ep_square")
+            .selected_text(
+                self.setup
+                    .ep_square
+                    .map(|square| square.to_string())
+                    .unwrap_or(String::from("-")),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.setup.ep_square, None, "-");
+                for file in File::ALL {
+                    let square = Square::from_coords(file, ep_rank);
+                    ui.selectable_value(
+                        &mut self.setup.ep_square,
+                        Some(square),
+                        square.to_string(),
+                    );
+                }
+            });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                self.setup = Setup::empty();
+            }
+            if ui.button("Initial").clicked() {
+                self.setup = Setup::initial();
+            }
+            if ui.button("Done").clicked() {
+                done = true;
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.separator();
+            ui.colored_label(Color32::DARK_RED, error);
+        }
+
+        done
+    }
+
+    /// Pick up pieces from the board and drop dragged pieces on it.
+    pub fn handle_input(&mut self, ui: &Ui, orientation: Color) {
+        let rect = ui.max_rect();
+        ui.input(|input| {
+            let Some(position) = input.pointer.interact_pos() else {
+                return;
+            };
+            let square = square_at(&rect, position, orientation);
+            if input.pointer.button_pressed(PointerButton::Primary) {
+                if self.dragged.is_none()
+                    && let Some(square) = square
+                {
+                    self.dragged = self.setup.board.remove_piece_at(square);
+                }
+            } else if input.pointer.button_released(PointerButton::Primary) {
+                // dropped off the board, the piece is gone
+                if let Some(piece) = self.dragged.take()
+                    && let Some(square) = square
+                {
+                    self.setup.board.set_piece_at(square, piece);
+                }
+            } else if input.pointer.button_clicked(PointerButton::Secondary)
+                && let Some(square) = square
+            {
+                let _ = self.setup.board.remove_piece_at(square);
+            }
+        });
+    }
+
+    pub fn render_dragged(&self, ctx: &Context, ui: &Ui, sources: &Sources<'_>) {
+        if let Some(piece) = self.dragged
+            && let Some(position) = ctx.pointer_latest_pos()
+            && let Some(image) = sources.get(piece.char().to_string())
+        {
+            let square_size = board_rect(&ui.max_rect()).width() / 8.0;
+            image.paint_at(
+                ui,
+                Rect::from_center_size(position, vec2(square_size, square_size)),
+            );
+        }
+    }
+}
@@ -7,84 +7,113 @@
 
 use std::{collections::HashMap, io::Cursor};
 
-use egui::{Image, ImageSource, Pos2, Rect, Vec2, pos2};
+use egui::{Context, Image, ImageSource};
 use image::{DynamicImage, ImageReader};
 
-fn image_data(image: &DynamicImage, pos: Pos2, size: Vec2) -> Vec<u8> {
+use crate::{
+    piece_set::{PieceImage, PieceSet},
+    theme::{SquareFill, Theme},
+};
+
+fn image_data(image: &DynamicImage) -> Vec<u8> {
     let mut bytes: Vec<u8> = Vec::new();
-    let rect = Rect::from_min_size(pos, size);
     image
-        .crop_imm(
-            rect.min.x as u32,
-            rect.min.y as u32,
-            rect.width() as u32,
-            rect.height() as u32,
-        )
         .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
         .expect("failed to write image");
     bytes
 }
 
-fn make_source<'a>(name: &'a str, data: Vec<u8>) -> Image<'a> {
+fn make_source<'a>(uri: String, data: Vec<u8>) -> Image<'a> {
     Image::new(ImageSource::Bytes {
-        uri: format!("image://{name}").into(),
+        uri: uri.into(),
         bytes: data.into(),
     })
 }
 
-fn piece_parameters(pw: f32, ph: f32) -> Vec<(&'static str, Pos2)> {
-    vec![
-        ("K", (pos2(0.0, ph))),
-        ("Q", (pos2(1.0 * pw, ph))),
-        ("R", (pos2(2.0 * pw, ph))),
-        ("B", (pos2(3.0 * pw, ph))),
-        ("N", (pos2(4.0 * pw, ph))),
-        ("P", (pos2(5.0 * pw, ph))),
-        ("k", (pos2(0.0 * pw, 0.0))),
-        ("q", (pos2(1.0 * pw, 0.0))),
-        ("r", (pos2(2.0 * pw, 0.0))),
-        ("b", (pos2(3.0 * pw, 0.0))),
-        ("n", (pos2(4.0 * pw, 0.0))),
-        ("p", (pos2(5.0 * pw, 0.0))),
-    ]
+pub fn dark_square_image() -> Option<DynamicImage> {
+    let bytes = include_bytes!("../assets/dark-square.png");
+    ImageReader::with_format(Cursor::new(bytes), image::ImageFormat::Png)
+        .decode()
+        .inspect_err(|err| log::error!("failed to decode dark square: {err}"))
+        .ok()
 }
 
 pub struct Sources<'a> {
     collection: HashMap<&'a str, Image<'a>>,
+    /// uris of the pieces, named after their set as egui caches images by uri
+    piece_uris: Vec<String>,
+    /// uris of the squares' textures, named after their theme
+    square_uris: Vec<String>,
 }
 
 impl<'a> Sources<'a> {
-    pub fn new() -> Self {
-        log::info!("Init image sources");
-        let mut collection = HashMap::new();
+    pub fn new(piece_set: &PieceSet, theme: &Theme) -> Self {
+        let mut sources = Self {
+            collection: HashMap::new(),
+            piece_uris: Vec::new(),
+            square_uris: Vec::new(),
+        };
+        sources.insert_pieces(piece_set);
+        sources.insert_squares(theme);
+        log::info!("image sources ready");
+        sources
+    }
 
-        let chessmen_set = include_bytes!("../assets/pieces-leipzig.png");
-        if let Ok(image) =
-            ImageReader::with_format(Cursor::new(chessmen_set), image::ImageFormat::Png).decode()
-        {
-            let width = image.width();
-            let height = image.height();
+    fn insert_pieces(&mut self, piece_set: &PieceSet) {
+        log::info!("Init image sources with {} pieces", piece_set.name);
+        // vectors go to egui's SVG loader, which draws them at the size they're shown
+        for (name, piece) in piece_set.pieces.iter() {
+            let (uri, data) = match piece {
+                PieceImage::Raster(image) => (
+                    format!("image://{}/{name}", piece_set.name),
+                    image_data(image),
+                ),
+                PieceImage::Vector(svg) => (
+                    format!("image://{}/{name}.svg", piece_set.name),
+                    svg.clone(),
+                ),
+            };
+            self.collection
+                .insert(*name, make_source(uri.clone(), data));
+            self.piece_uris.push(uri);
+        }
+    }
 
-            let pw = width as f32 / 6.0;
-            let ph = height as f32 / 2.0;
-            let size = Vec2::new(pw, ph);
+    fn insert_squares(&mut self, theme: &Theme) {
+        for (name, fill) in [("light-square", &theme.light), ("dark-square", &theme.dark)] {
+            let (uri, data) = match fill {
+                SquareFill::Color(_) => {
+                    self.collection.remove(name);
+                    continue;
+                }
+                SquareFill::Hatching => (
+                    String::from("image://dark-square"),
+                    include_bytes!("../assets/dark-square.png").to_vec(),
+                ),
+                SquareFill::Texture(image) => (
+                    format!("image://theme/{}/{name}", theme.name),
+                    image_data(image),
+                ),
+            };
+            self.collection.insert(name, make_source(uri.clone(), data));
+            self.square_uris.push(uri);
+        }
+    }
 
-            for (name, pos) in piece_parameters(pw, ph) {
-                let image_data = image_data(&image, pos, size);
-                collection.insert(name, make_source(name, image_data));
-            }
+    /// Switch to other pieces, dropping the previous ones from egui's caches.
+    pub fn set_pieces(&mut self, ctx: &Context, piece_set: &PieceSet) {
+        for uri in self.piece_uris.drain(..) {
+            ctx.forget_image(&uri);
         }
+        self.insert_pieces(piece_set);
+    }
 
-        let bytes = include_bytes!("../assets/dark-square.png");
-        collection.insert(
-            "dark-square",
-            Image::new(ImageSource::Bytes {
-                uri: "image://dark-square".into(),
-                bytes: bytes.into(),
-            }),
-        );
-        log::info!("image sources ready");
-        Self { collection }
+    /// Switch to the textures of another theme.
+    pub fn set_theme(&mut self, ctx: &Context, theme: &Theme) {
+        for uri in self.square_uris.drain(..) {
+            ctx.forget_image(&uri);
+        }
+        self.insert_squares(theme);
     }
 
     pub fn get<S>(&self, name: S) -> Option<&Image<'a>>
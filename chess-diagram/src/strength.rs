@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use egui::Ui;
+use ucui_engine::Strength;
+
+/// A strength to play against, by its name
+pub struct StrengthPreset {
+    pub name: &'static str,
+    pub strength: Strength,
+}
+
+const fn preset(
+    name: &'static str,
+    elo: u32,
+    nodes: u64,
+    movetime: u64,
+    candidates: u16,
+    margin: i32,
+) -> StrengthPreset {
+    StrengthPreset {
+        name,
+        strength: Strength {
+            elo,
+            nodes: Some(nodes),
+            movetime: Some(Duration::from_millis(movetime)),
+            candidates,
+            margin,
+        },
+    }
+}
+
+/// From the weakest, engines without an Elo setting searching little and
+/// picking moves further from their best line the weaker they are.
+pub const PRESETS: [StrengthPreset; 7] = [
+    preset("beginner", 800, 50, 200, 5, 300),
+    preset("novice", 1000, 200, 300, 4, 200),
+    preset("casual", 1200, 1_000, 500, 3, 120),
+    preset("club", 1500, 5_000, 700, 3, 60),
+    preset("strong", 1800, 20_000, 1_000, 2, 30),
+    preset("expert", 2100, 100_000, 1_500, 1, 0),
+    preset("master", 2400, 500_000, 2_000, 1, 0),
+];
+
+pub fn find_preset(name: &str) -> Option<&'static StrengthPreset> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+/// A preset's name and rating, as shown in the menu
+pub fn preset_label(preset: &StrengthPreset) -> String {
+    format!("{} (~{})", preset.name, preset.strength.elo)
+}
+
+pub enum StrengthAction {
+    /// a preset by its name, full strength without one
+    Pick(Option<&'static str>),
+    Close,
+}
+
+/// Picks the strength of the engine we play against, `current` being
+/// the name of the preset it plays at.
+pub fn render_panel(ui: &mut Ui, current: Option<&str>) -> Option<StrengthAction> {
+    let mut action = None;
+
+    ui.heading("Strength");
+    ui.separator();
+    if ui
+        .selectable_label(current.is_none(), "full strength")
+        .clicked()
+    {
+        action = Some(StrengthAction::Pick(None));
+    }
+    for preset in PRESETS.iter() {
+        if ui
+            .selectable_label(current == Some(preset.name), preset_label(preset))
+            .clicked()
+        {
+            action = Some(StrengthAction::Pick(Some(preset.name)));
+        }
+    }
+
+    ui.separator();
+    if ui.button("Close").clicked() {
+        action = Some(StrengthAction::Close);
+    }
+
+    action
+}
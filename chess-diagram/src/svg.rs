@@ -0,0 +1,236 @@
+use std::{fmt::Write, io::Cursor};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use egui::{Align, Color32, Rect, pos2, vec2};
+use image::{DynamicImage, ImageFormat};
+use shakmaty::{Color, Setup};
+
+use crate::{
+    board::{FRAME_GAP, MARGIN, coordinate_labels, square_from_indices},
+    theme::{Font, SquareFill, Theme},
+};
+
+const PIECES_SVG: &str = include_str!("../assets/pieces-leipzig.svg");
+/// side of a piece in the sprite, 6 columns by 2 rows
+const SPRITE_CELL: f32 = 34.0;
+
+pub const DEFAULT_SIZE: f32 = 600.0;
+
+const SVG_NAMESPACES: &str = r#"xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd""#;
+
+/// The pieces' layers of the Leipzig sprite, without its hidden background.
+fn sprite_layers() -> &'static str {
+    let start = PIECES_SVG
+        .find("id=\"layer4\"")
+        .and_then(|id| PIECES_SVG[..id].rfind("<g"))
+        .unwrap_or(0);
+    let end = PIECES_SVG.rfind("</svg>").unwrap_or(PIECES_SVG.len());
+    &PIECES_SVG[start..end]
+}
+
+fn sprite_cell(piece_char: char) -> Option<(f32, f32)> {
+    let column = "kqrbnp".find(piece_char.to_ascii_lowercase())? as f32;
+    let row = if piece_char.is_ascii_uppercase() {
+        1.0
+    } else {
+        0.0
+    };
+    Some((column * SPRITE_CELL, row * SPRITE_CELL))
+}
+
+/// A piece of the Leipzig sprite as an SVG document of its own.
+pub fn piece_svg(piece_char: char) -> Option<String> {
+    let (cell_x, cell_y) = sprite_cell(piece_char)?;
+    Some(format!(
+        r#"<svg {SVG_NAMESPACES} width="{SPRITE_CELL}" height="{SPRITE_CELL}" viewBox="{cell_x} {cell_y} {SPRITE_CELL} {SPRITE_CELL}">{}</svg>"#,
+        sprite_layers()
+    ))
+}
+
+/// A color as SVG attributes, `paint` being "fill" or "stroke".
+fn paint(paint: &str, color: Color32) -> String {
+    let [red, green, blue, alpha] = color.to_srgba_unmultiplied();
+    format!(
+        r##"{paint}="#{red:02x}{green:02x}{blue:02x}" {paint}-opacity="{:.3}""##,
+        alpha as f32 / 255.0
+    )
+}
+
+fn font_family(font: Font) -> &'static str {
+    match font {
+        Font::Proportional => "sans-serif",
+        Font::Monospace => "monospace",
+    }
+}
+
+/// A texture as a data URL, for `<image>` to embed it.
+fn data_url(image: &DynamicImage) -> String {
+    let mut bytes = Vec::new();
+    if let Err(err) = image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png) {
+        log::error!("failed to encode a texture: {err}");
+    }
+    format!("data:image/png;base64,{}", STANDARD.encode(bytes))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a position as a standalone SVG document of `size` pixels,
+/// laid out as `render_board` does it on screen.
+pub fn render_svg(
+    position: &Setup,
+    theme: &Theme,
+    title: Option<&str>,
+    orientation: Color,
+    size: f32,
+) -> String {
+    let size = size.max(2.0 * MARGIN + 64.0);
+    let board_size = size - 2.0 * MARGIN;
+    let square_size = board_size / 8.0;
+    let (min_x, min_y) = (MARGIN, MARGIN);
+    let (max_x, max_y) = (MARGIN + board_size, MARGIN + board_size);
+    // the dark squares' hatching
+    let hatch = square_size / 7.5 / std::f32::consts::SQRT_2;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg {SVG_NAMESPACES} width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    );
+    let _ = writeln!(svg, "<defs>");
+    let _ = writeln!(
+        svg,
+        r#"<pattern id="hatching" width="{hatch:.3}" height="{hatch:.3}" patternUnits="userSpaceOnUse" patternTransform="rotate(-45)"><line x1="0" y1="{:.3}" x2="{hatch:.3}" y2="{:.3}" stroke="black" stroke-width="{:.3}"/></pattern>"#,
+        hatch / 2.0,
+        hatch / 2.0,
+        (square_size / 60.0).max(0.6),
+    );
+    // textures start again on each square
+    for (id, fill) in [("light-square", &theme.light), ("dark-square", &theme.dark)] {
+        if let SquareFill::Texture(image) = fill {
+            let _ = writeln!(
+                svg,
+                r#"<pattern id="{id}" x="{min_x}" y="{min_y}" width="{square_size:.3}" height="{square_size:.3}" patternUnits="userSpaceOnUse"><image width="{square_size:.3}" height="{square_size:.3}" preserveAspectRatio="none" xlink:href="{}"/></pattern>"#,
+                data_url(image)
+            );
+        }
+    }
+    let _ = writeln!(svg, r#"<g id="leipzig">{}</g>"#, sprite_layers());
+    let _ = writeln!(svg, "</defs>");
+
+    // background
+    let _ = writeln!(
+        svg,
+        r#"<rect x="0" y="0" width="{size}" height="{size}" {}/>"#,
+        paint("fill", theme.background)
+    );
+
+    // title
+    if let Some(title) = title {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" font-family="{}" font-size="16" text-anchor="middle" dominant-baseline="hanging" {}>{}</text>"#,
+            min_x + board_size / 2.0,
+            MARGIN / 3.0,
+            font_family(theme.coordinates.font),
+            paint("fill", theme.coordinates.color),
+            escape(title),
+        );
+    }
+
+    // frame
+    if theme.frame.width > 0.0 {
+        let offset = FRAME_GAP + theme.frame.width / 2.0;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" rx="1" fill="none" {} stroke-width="{}"/>"#,
+            min_x - offset,
+            min_y - offset,
+            board_size + 2.0 * offset,
+            board_size + 2.0 * offset,
+            paint("stroke", theme.frame.color),
+            theme.frame.width,
+        );
+    }
+
+    // turn
+    let turn_x = max_x + MARGIN * 0.7;
+    let turn_y = if position.turn == orientation {
+        max_y - MARGIN / 3.0
+    } else {
+        min_y + MARGIN / 3.0
+    };
+    if position.turn == Color::Black {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{turn_x:.2}" cy="{turn_y:.2}" r="12" fill="black"/>"#
+        );
+    } else {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{turn_x:.2}" cy="{turn_y:.2}" r="9" fill="white" stroke="black" stroke-width="3"/>"#
+        );
+    }
+
+    for rank_index in 0..8usize {
+        for file_index in 0..8usize {
+            let top = rank_index as f32 * square_size + min_y;
+            let left = file_index as f32 * square_size + min_x;
+            let square = square_from_indices(rank_index, file_index, orientation);
+            let (fill, id) = if square.is_dark() {
+                (&theme.dark, "dark-square")
+            } else {
+                (&theme.light, "light-square")
+            };
+            let fill = match fill {
+                SquareFill::Color(color) => paint("fill", *color),
+                SquareFill::Hatching => String::from(r##"fill="url(#hatching)""##),
+                SquareFill::Texture(_) => format!(r##"fill="url(#{id})""##),
+            };
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{left:.2}" y="{top:.2}" width="{square_size:.2}" height="{square_size:.2}" {fill}/>"#
+            );
+            if let Some(piece) = position.board.piece_at(square)
+                && let Some((cell_x, cell_y)) = sprite_cell(piece.char())
+            {
+                let _ = writeln!(
+                    svg,
+                    r##"<svg x="{left:.2}" y="{top:.2}" width="{square_size:.2}" height="{square_size:.2}" viewBox="{cell_x} {cell_y} {SPRITE_CELL} {SPRITE_CELL}"><use xlink:href="#leipzig"/></svg>"##
+                );
+            }
+        }
+    }
+
+    // coordinates
+    let board_rect = Rect::from_min_size(pos2(min_x, min_y), vec2(board_size, board_size));
+    for label in coordinate_labels(&board_rect, orientation, theme.coordinates.placement, None) {
+        let anchor = match label.align.x() {
+            Align::Min => "start",
+            Align::Center => "middle",
+            Align::Max => "end",
+        };
+        let baseline = match label.align.y() {
+            Align::Min => "text-before-edge",
+            Align::Center => "central",
+            Align::Max => "text-after-edge",
+        };
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" font-family="{}" font-size="{}" text-anchor="{anchor}" dominant-baseline="{baseline}" xml:space="preserve" {}>{}</text>"#,
+            label.pos.x,
+            label.pos.y,
+            font_family(theme.coordinates.font),
+            theme.coordinates.size,
+            paint("fill", theme.coordinates.color),
+            escape(&label.text),
+        );
+    }
+
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
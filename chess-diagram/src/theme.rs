@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use egui::{Color32, FontFamily};
+use image::DynamicImage;
+use serde::{Deserialize, Deserializer, de::Error};
+
+pub const DEFAULT_THEME: &str = "classic";
+
+const BUILTIN_THEMES: [(&str, &str); 2] = [
+    ("wood", include_str!("../assets/themes/wood.toml")),
+    ("blue", include_str!("../assets/themes/blue.toml")),
+];
+
+/// How squares of a color are filled.
+pub enum SquareFill {
+    Color(Color32),
+    /// the lines of printed diagrams, as `dark-square.png` draws them
+    Hatching,
+    Texture(DynamicImage),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    /// in the margins, as printed diagrams have them
+    Outside,
+    /// in the corners of the edge squares
+    Inside,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Font {
+    Proportional,
+    Monospace,
+}
+
+impl Font {
+    pub fn family(&self) -> FontFamily {
+        match self {
+            Font::Proportional => FontFamily::Proportional,
+            Font::Monospace => FontFamily::Monospace,
+        }
+    }
+}
+
+/// A stroke around the board, a few pixels away from it.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Frame {
+    pub width: f32,
+    #[serde(deserialize_with = "hex_color")]
+    pub color: Color32,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            width: 6.0,
+            color: Color32::BLACK,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Coordinates {
+    pub placement: Placement,
+    pub font: Font,
+    pub size: f32,
+    /// also the color of the title
+    #[serde(deserialize_with = "hex_color")]
+    pub color: Color32,
+}
+
+impl Default for Coordinates {
+    fn default() -> Self {
+        Self {
+            placement: Placement::Outside,
+            font: Font::Proportional,
+            size: 24.0,
+            color: Color32::BLACK,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Highlights {
+    /// over both squares of the last move
+    #[serde(deserialize_with = "hex_color")]
+    pub last_move: Color32,
+    /// over the square a piece is taken from, in click mode
+    #[serde(deserialize_with = "hex_color")]
+    pub selected: Color32,
+    /// of the engine's best moves, the weaker ones fading out
+    #[serde(deserialize_with = "hex_color")]
+    pub arrows: Color32,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Self {
+            last_move: Color32::from_rgba_unmultiplied(0, 200, 0, 24),
+            selected: Color32::from_rgba_unmultiplied(0, 183, 235, 24),
+            arrows: Color32::from_rgb(0, 90, 200),
+        }
+    }
+}
+
+/// A theme as written in a file, anything missing being the classic one.
+///
+/// ```toml
+/// background = "#f4ecdc"
+/// light = "#f0d9b5"
+/// # a color, "hatching" or an image next to the theme
+/// dark = "walnut.png"
+///
+/// [frame]
+/// width = 4
+/// color = "#5c3d21"
+///
+/// [coordinates]
+/// placement = "inside"  # or "outside"
+/// font = "monospace"    # or "proportional"
+/// size = 14
+/// color = "#5c3d21"
+///
+/// [highlights]
+/// last_move = "#cdd26a99"
+/// selected = "#14551e80"
+/// arrows = "#15781b"
+/// ```
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    #[serde(deserialize_with = "hex_color")]
+    background: Color32,
+    light: String,
+    dark: String,
+    frame: Frame,
+    coordinates: Coordinates,
+    highlights: Highlights,
+}
+
+impl Default for ThemeFile {
+    fn default() -> Self {
+        Self {
+            background: Color32::WHITE,
+            light: String::from("#ffffff"),
+            dark: String::from("hatching"),
+            frame: Frame::default(),
+            coordinates: Coordinates::default(),
+            highlights: Highlights::default(),
+        }
+    }
+}
+
+fn hex_color<'de, D>(deserializer: D) -> Result<Color32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    Color32::from_hex(&hex).map_err(|_| {
+        D::Error::custom(format!(
+            "invalid color '{hex}', expected #rrggbb or #rrggbbaa"
+        ))
+    })
+}
+
+/// Colors and textures of the board, and how it's framed and labelled.
+pub struct Theme {
+    pub name: String,
+    pub background: Color32,
+    pub light: SquareFill,
+    pub dark: SquareFill,
+    pub frame: Frame,
+    pub coordinates: Coordinates,
+    pub highlights: Highlights,
+}
+
+impl Theme {
+    /// A built-in theme by its name, or a theme from a TOML file.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if name == DEFAULT_THEME {
+            return Ok(Self::classic());
+        }
+        match BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name) {
+            Some((name, content)) => {
+                Self::parse(name, content, None).map_err(|err| format!("theme {name}: {err}"))
+            }
+            None => Self::from_file(Path::new(name)),
+        }
+    }
+
+    /// White and hatched squares in a black frame, as printed diagrams.
+    pub fn classic() -> Self {
+        Self::from_theme_file(DEFAULT_THEME, ThemeFile::default(), None)
+            .expect("the classic theme has no texture to load")
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        if !path.is_file() {
+            return Err(format!(
+                "unknown theme '{}', not one of {} nor a file",
+                path.display(),
+                builtin_names().join(", ")
+            ));
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::parse(&name, &content, path.parent())
+            .map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Textures are looked for in `dir`, built-in themes have none.
+    fn parse(name: &str, content: &str, dir: Option<&Path>) -> Result<Self, String> {
+        let theme_file: ThemeFile = toml::from_str(content).map_err(|err| err.to_string())?;
+        Self::from_theme_file(name, theme_file, dir)
+    }
+
+    fn from_theme_file(
+        name: &str,
+        theme_file: ThemeFile,
+        dir: Option<&Path>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            name: name.to_string(),
+            background: theme_file.background,
+            light: square_fill(&theme_file.light, dir)?,
+            dark: square_fill(&theme_file.dark, dir)?,
+            frame: theme_file.frame,
+            coordinates: theme_file.coordinates,
+            highlights: theme_file.highlights,
+        })
+    }
+}
+
+pub fn builtin_names() -> Vec<&'static str> {
+    let mut names = vec![DEFAULT_THEME];
+    names.extend(BUILTIN_THEMES.iter().map(|(name, _)| *name));
+    names
+}
+
+fn square_fill(value: &str, dir: Option<&Path>) -> Result<SquareFill, String> {
+    if value.starts_with('#') {
+        return Color32::from_hex(value)
+            .map(SquareFill::Color)
+            .map_err(|_| format!("invalid color '{value}', expected #rrggbb or #rrggbbaa"));
+    }
+    if value == "hatching" {
+        return Ok(SquareFill::Hatching);
+    }
+    let path = match dir {
+        Some(dir) => dir.join(value),
+        None => PathBuf::from(value),
+    };
+    image::open(&path)
+        .map(SquareFill::Texture)
+        .map_err(|err| format!("failed to load {}: {err}", path.display()))
+}
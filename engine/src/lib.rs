@@ -1,10 +1,18 @@
-use std::sync::mpsc::RecvError;
+use std::{fmt, str::FromStr, sync::mpsc::RecvError, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use shakmaty::Move;
-use shakmaty_uci::UciInfo;
+use shakmaty_uci::{UciInfo, UciMove};
+mod limits;
+mod option;
+mod process;
+mod strength;
 mod uci;
 
+pub use limits::SearchLimits;
+pub use option::{EngineOption, OptionKind, find_option};
+pub use strength::Strength;
+
 #[derive(Clone, Eq, PartialEq, Debug, Default)]
 pub enum EngineState {
     #[default]
@@ -14,22 +22,101 @@ pub enum EngineState {
     Move(Move),
 }
 
-// #[derive(Serialize, Deserialize)]
-// pub enum EngineGoArg {
-//     MoveTime(Duration),
-//     LeftTime(Duration),
-//     Depth(usize),
-// }
+/// Base time in minutes plus an increment in seconds, as in "5+3"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        let base: f64 = base
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid base time '{base}'"))?;
+        let increment: f64 = increment
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid increment '{increment}'"))?;
+        if base <= 0.0 || increment < 0.0 {
+            return Err(format!("invalid time control '{s}'"));
+        }
+        Ok(Self {
+            base: Duration::from_secs_f64(base * 60.0),
+            increment: Duration::from_secs_f64(increment),
+        })
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.base.as_secs_f64() / 60.0,
+            self.increment.as_secs_f64()
+        )
+    }
+}
+
+/// Options as "ID[:VALUE]", the value running to the end as in
+/// "SyzygyPath:/a:/b"
+pub fn parse_uci_options(options: &[String]) -> Vec<(String, Option<String>)> {
+    options
+        .iter()
+        .map(|option| match option.split_once(':') {
+            Some((id, value)) => (id.to_string(), Some(value.to_string())),
+            None => (option.to_string(), None),
+        })
+        .collect()
+}
+
+/// Clocks of both sides when the engine is asked for a move
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ClockTime {
+    pub white_time: Duration,
+    pub black_time: Duration,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+}
+
+/// Tags a search request, its replies carrying it back so that those of
+/// an earlier request can be told apart.
+pub type SearchId = u64;
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "_tag")]
 pub enum EngineCommand {
-    Go { fen: String, depth: u8 },
+    Go {
+        id: SearchId,
+        fen: String,
+        limits: SearchLimits,
+    },
+    /// Search without limit, reporting progress on `multi_pv` lines until stopped
+    Analyse {
+        id: SearchId,
+        fen: String,
+        multi_pv: u16,
+    },
     NewGame,
+    /// Interrupt the search, its best move is dropped
     Stop,
+    /// Set while not searching, a running analysis starting again after
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    /// For the moves to come, full strength without one
+    SetStrength {
+        strength: Option<Strength>,
+    },
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(tag = "_tag")]
 pub enum Score {
     CentiPawns {
@@ -61,28 +148,123 @@ impl From<UciInfo> for Score {
     }
 }
 
+/// Progress of a running search.
+///
+/// Like `Score`, it's seen from the side to move.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: Option<u8>,
+    pub score: Score,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub pv: Vec<UciMove>,
+}
+
+impl From<UciInfo> for SearchInfo {
+    fn from(value: UciInfo) -> Self {
+        Self {
+            depth: value.depth,
+            nodes: value.nodes,
+            nps: value.nps,
+            pv: value.pv.clone(),
+            score: Score::from(value),
+        }
+    }
+}
+
+/// Messages from the engine, tagged with the position searched.
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "_tag")]
 pub enum EngineMessage {
-    Id(String),
+    /// The engine's name and the options it declares
+    Id {
+        name: String,
+        options: Vec<EngineOption>,
+    },
+    /// The move found by the search `id` in `fen`
     BestMove {
+        id: SearchId,
+        fen: String,
         move_: ucui_utils::MoveSerde,
         score: Score,
     },
+    /// Candidate lines, best first
+    Info {
+        id: SearchId,
+        fen: String,
+        lines: Vec<SearchInfo>,
+    },
+    Error(EngineError),
+}
+
+/// What can go wrong with an engine, the app going on without it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "_tag")]
+pub enum EngineError {
+    /// its program couldn't be started
+    Spawn { path: String, message: String },
+    /// it exited, or closed its output
+    Died,
+    /// it didn't answer as a UCI engine
+    NotUci,
+    /// its best move isn't legal in the position it was given
+    IllegalMove { fen: String, move_: String },
+    /// it was given a position that can't be played
+    InvalidPosition { fen: String },
+    /// an option it doesn't have, or a value it doesn't take
+    InvalidOption { message: String },
 }
 
+impl EngineError {
+    /// Whether the engine is gone and needs to be started again
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            EngineError::Spawn { .. } | EngineError::Died | EngineError::NotUci
+        )
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Spawn { path, message } => write!(f, "failed to start {path}: {message}"),
+            EngineError::Died => write!(f, "the engine died"),
+            EngineError::NotUci => write!(f, "not a UCI engine"),
+            EngineError::IllegalMove { fen, move_ } => {
+                write!(f, "illegal move {move_} played in {fen}")
+            }
+            EngineError::InvalidPosition { fen } => write!(f, "invalid position {fen}"),
+            EngineError::InvalidOption { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
 pub trait Engine {
     fn name(&self) -> String;
     fn new_game(&self) {}
     fn stop(&self) {}
-    fn go(&self, fen: String, depth: u8);
+    fn go(&self, id: SearchId, fen: String, limits: SearchLimits);
+    fn analyse(&self, id: SearchId, fen: String, multi_pv: u16);
+    /// The options declared by the engine
+    fn options(&self) -> Vec<EngineOption> {
+        Vec::new()
+    }
+    fn set_option(&self, _name: String, _value: Option<String>) {}
+    fn set_strength(&self, _strength: Option<Strength>) {}
     fn recv(&self) -> Result<EngineMessage, RecvError>;
+    /// Let the engine go, `recv` then fails once it's gone
+    fn quit(&self) {}
 }
 
+/// Start an engine, failing if it can't run or doesn't speak UCI.
 pub fn connect_engine(
     engine_path: &str,
     args: Option<Vec<String>>,
     options: Vec<(String, Option<String>)>,
-) -> Box<dyn Engine + Send> {
-    Box::new(uci::connect_engine(engine_path, args, options))
+) -> Result<Box<dyn Engine + Send + Sync>, EngineError> {
+    uci::connect_engine(engine_path, args, options)
+        .map(|engine| Box::new(engine) as Box<dyn Engine + Send + Sync>)
 }
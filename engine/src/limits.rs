@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use shakmaty_uci::{UciMessage, UciMove, UciSearchControl, UciTimeControl};
+
+use crate::ClockTime;
+
+/// What bounds a search, as `go` takes it.
+///
+/// The search stops at the first limit reached. Pondering, an infinite
+/// search, a time per move and the clocks exclude each other, the first
+/// one set in this order being sent. Asked for a move, pondering or an
+/// infinite search is stopped after `movetime` instead, and goes on until
+/// stopped without it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// look for a mate in this many moves
+    pub mate: Option<u8>,
+    /// search until stopped
+    pub infinite: bool,
+    /// search the position after the expected move, until stopped
+    pub ponder: bool,
+    /// only these moves, all of them if empty
+    pub searchmoves: Vec<UciMove>,
+    /// the clocks of both sides, for the engine to manage its time
+    pub time: Option<ClockTime>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn go_message(&self) -> UciMessage {
+        let time_control = if self.ponder {
+            Some(UciTimeControl::Ponder)
+        } else if self.infinite {
+            Some(UciTimeControl::Infinite)
+        } else if let Some(movetime) = self.movetime {
+            Some(UciTimeControl::MoveTime(movetime))
+        } else {
+            self.time.map(|time| UciTimeControl::TimeLeft {
+                white_time: Some(time.white_time),
+                black_time: Some(time.black_time),
+                white_increment: Some(time.white_increment),
+                black_increment: Some(time.black_increment),
+                moves_to_go: None,
+            })
+        };
+        let search_control = UciSearchControl {
+            search_moves: self.searchmoves.clone(),
+            mate: self.mate,
+            depth: self.depth,
+            nodes: self.nodes,
+        };
+        let bounded = !search_control.search_moves.is_empty()
+            || search_control.mate.is_some()
+            || search_control.depth.is_some()
+            || search_control.nodes.is_some();
+        UciMessage::Go {
+            time_control,
+            search_control: bounded.then_some(search_control),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use shakmaty_uci::UciOptionConfig;
+
+/// The type of an option, with its default and what it accepts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "_tag")]
+pub enum OptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    Combo {
+        default: String,
+        vars: Vec<String>,
+    },
+    String {
+        default: String,
+    },
+    /// an action, without a value
+    Button,
+}
+
+/// An option as the engine declares it during the `uci` handshake.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EngineOption {
+    pub name: String,
+    pub kind: OptionKind,
+}
+
+impl EngineOption {
+    /// The default value as `setoption` takes it, none for a button
+    pub fn default_value(&self) -> Option<String> {
+        match &self.kind {
+            OptionKind::Check { default } => Some(default.to_string()),
+            OptionKind::Spin { default, .. } => Some(default.to_string()),
+            OptionKind::Combo { default, .. } | OptionKind::String { default } => {
+                Some(default.clone())
+            }
+            OptionKind::Button => None,
+        }
+    }
+
+    /// Check a value against the type of the option, and its range for a spin.
+    pub fn validate(&self, value: Option<&str>) -> Result<(), String> {
+        let name = &self.name;
+        match (&self.kind, value) {
+            (OptionKind::Button, None) => Ok(()),
+            (OptionKind::Button, Some(_)) => Err(format!("{name} is a button, it takes no value")),
+            (_, None) => Err(format!("{name} needs a value")),
+            (OptionKind::Check { .. }, Some(value)) => match value {
+                "true" | "false" => Ok(()),
+                _ => Err(format!("{name} is either true or false, not '{value}'")),
+            },
+            (OptionKind::Spin { min, max, .. }, Some(value)) => {
+                let number: i64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{name} is a number, not '{value}'"))?;
+                match (min, max) {
+                    (Some(min), _) if number < *min => {
+                        Err(format!("{name} is at least {min}, not {number}"))
+                    }
+                    (_, Some(max)) if number > *max => {
+                        Err(format!("{name} is at most {max}, not {number}"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (OptionKind::Combo { vars, .. }, Some(value)) => {
+                if vars.iter().any(|var| var.eq_ignore_ascii_case(value)) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "{name} is one of {}, not '{value}'",
+                        vars.join(", ")
+                    ))
+                }
+            }
+            (OptionKind::String { .. }, Some(_)) => Ok(()),
+        }
+    }
+}
+
+impl From<UciOptionConfig> for EngineOption {
+    fn from(value: UciOptionConfig) -> Self {
+        match value {
+            UciOptionConfig::Check { name, default } => EngineOption {
+                name,
+                kind: OptionKind::Check {
+                    default: default.unwrap_or(false),
+                },
+            },
+            UciOptionConfig::Spin {
+                name,
+                default,
+                min,
+                max,
+            } => EngineOption {
+                name,
+                kind: OptionKind::Spin {
+                    default: default.or(min).unwrap_or(0),
+                    min,
+                    max,
+                },
+            },
+            UciOptionConfig::Combo { name, default, var } => EngineOption {
+                name,
+                kind: OptionKind::Combo {
+                    default: default.or_else(|| var.first().cloned()).unwrap_or_default(),
+                    vars: var,
+                },
+            },
+            UciOptionConfig::String { name, default } => EngineOption {
+                name,
+                kind: OptionKind::String {
+                    default: default.unwrap_or_default(),
+                },
+            },
+            UciOptionConfig::Button { name } => EngineOption {
+                name,
+                kind: OptionKind::Button,
+            },
+        }
+    }
+}
+
+/// The option named so, UCI names being case insensitive
+pub fn find_option<'a>(options: &'a [EngineOption], name: &str) -> Option<&'a EngineOption> {
+    options
+        .iter()
+        .find(|option| option.name.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,76 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::Sender,
+    thread,
+    time::Duration,
+};
+
+use crate::uci::Input;
+
+/// An engine running as a child process.
+///
+/// Its output is read on a separate thread and sent line by line,
+/// so that we never block on an engine that's thinking.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl EngineProcess {
+    pub fn spawn(path: &str, args: Option<Vec<String>>, lines: Sender<Input>) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .args(args.unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or(io::Error::other("no stdin for the engine"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or(io::Error::other("no stdout for the engine"))?;
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        log::debug!("<engine> {line}");
+                        if lines.send(Input::Line(line)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        log::error!("<engine> failed to read: {err}");
+                        break;
+                    }
+                }
+            }
+            let _ = lines.send(Input::Closed);
+        });
+
+        Ok(Self { child, stdin })
+    }
+
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        log::debug!(">engine> {command}");
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
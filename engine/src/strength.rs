@@ -0,0 +1,75 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use shakmaty_uci::{UciInfo, UciMove};
+
+use crate::{EngineOption, OptionKind, Score, find_option};
+
+/// How well the engine plays its moves, analysis going on at full strength.
+///
+/// An engine declaring `UCI_LimitStrength` and an `UCI_Elo` range holding
+/// `elo` plays at that rating. Others search within the limits below, and
+/// pick their move among the best lines.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Strength {
+    pub elo: u32,
+    /// nodes searched for a move
+    pub nodes: Option<u64>,
+    /// time spent on a move
+    pub movetime: Option<Duration>,
+    /// lines searched, the move being one of them at random
+    pub candidates: u16,
+    /// how much worse than the best line a candidate can be, in centipawns
+    pub margin: i32,
+}
+
+impl Strength {
+    /// The rating to set, if the engine can limit itself to it.
+    pub fn uci_elo(&self, declared: &[EngineOption]) -> Option<i64> {
+        let limit = find_option(declared, "UCI_LimitStrength")?;
+        let elo = find_option(declared, "UCI_Elo")?;
+        match (&limit.kind, &elo.kind) {
+            (OptionKind::Check { .. }, OptionKind::Spin { min, max, .. }) => {
+                let wanted = self.elo as i64;
+                let above = min.is_none_or(|min| wanted >= min);
+                let below = max.is_none_or(|max| wanted <= max);
+                (above && below).then_some(wanted)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A score to compare lines by, mates first
+fn centipawns(score: &Score) -> Option<i32> {
+    match score {
+        Score::CentiPawns { score, .. } => Some(*score),
+        Score::Mate { moves } if *moves > 0 => Some(100_000 - *moves as i32),
+        Score::Mate { moves } => Some(-100_000 - *moves as i32),
+        Score::None => None,
+    }
+}
+
+/// A move at random among the lines close enough to the best one
+pub(crate) fn pick_candidate(
+    lines: &BTreeMap<u16, UciInfo>,
+    margin: i32,
+) -> Option<(UciMove, Score)> {
+    let scored: Vec<(UciMove, Score, i32)> = lines
+        .values()
+        .filter_map(|info| {
+            let score = Score::from(info.clone());
+            let value = centipawns(&score)?;
+            Some((info.pv.first()?.clone(), score, value))
+        })
+        .collect();
+    let best = scored.iter().map(|(_, _, value)| *value).max()?;
+    let candidates: Vec<&(UciMove, Score, i32)> = scored
+        .iter()
+        .filter(|(_, _, value)| best - value <= margin)
+        .collect();
+    candidates
+        .choose(&mut rand::rng())
+        .map(|(move_, score, _)| (move_.clone(), score.clone()))
+}
@@ -1,99 +1,406 @@
 use std::{
+    collections::{BTreeMap, VecDeque},
     str::FromStr,
-    sync::mpsc::{Receiver, RecvError, Sender, channel},
+    sync::{
+        Mutex,
+        mpsc::{Receiver, RecvError, RecvTimeoutError, Sender, channel},
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use shakmaty::{Chess, Color, FromSetup, Position, fen::Fen};
-use shakmaty_uci::{UciInfo, UciInfoScore, UciMessage, UciMove};
+use shakmaty::{Chess, FromSetup, fen::Fen};
+use shakmaty_uci::{UciInfo, UciMessage, UciMove};
 
-use crate::Score;
+use crate::{
+    EngineError, EngineOption, Score, SearchId, SearchInfo, SearchLimits, Strength, find_option,
+    process::EngineProcess, strength::pick_candidate,
+};
 
 use super::{Engine, EngineCommand, EngineMessage};
 
+/// How long the engine has to answer `uci`, `isready` or `stop`
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the engine thread waits on: our commands and the engine's output
+pub(crate) enum Input {
+    Command(EngineCommand),
+    Line(String),
+    /// the engine's output is closed, it's gone
+    Closed,
+    /// the connection was dropped
+    Quit,
+}
+
+enum SearchKind {
+    /// the move is picked among the lines within `margin` of the best one, if any
+    BestMove {
+        margin: Option<i32>,
+    },
+    Analysis,
+}
+
+struct Search {
+    id: SearchId,
+    fen_string: String,
+    fen: Fen,
+    kind: SearchKind,
+    // latest info for each `multipv` index
+    lines: BTreeMap<u16, UciInfo>,
+    // when an endless search is stopped for its move
+    stop_at: Option<Instant>,
+}
+
 struct UciEngine {
-    rx: Receiver<EngineCommand>,
+    rx: Receiver<Input>,
     tx: Sender<EngineMessage>,
 
-    engine: uci::Engine,
+    engine: EngineProcess,
     options: Vec<(String, Option<String>)>,
+    // as the engine declares them
+    declared: Vec<EngineOption>,
+    // options set during a search, for after it
+    deferred: Vec<(String, Option<String>)>,
+    search: Option<Search>,
+    // value of the MultiPV option, as asked when playing and as currently set
+    play_multi_pv: u16,
+    multi_pv: u16,
+    // for the moves to come, and the rating the engine is limited to
+    strength: Option<Strength>,
+    limited_elo: Option<i64>,
+    // commands received while waiting on the engine
+    pending: VecDeque<EngineCommand>,
+    // the engine's output got closed
+    closed: bool,
+    // the connection was dropped while waiting on the engine
+    quit: bool,
 }
 
 impl UciEngine {
     fn new(
-        path: &str,
-        rx: Receiver<EngineCommand>,
+        engine: EngineProcess,
+        rx: Receiver<Input>,
         tx: Sender<EngineMessage>,
-        args: Option<Vec<String>>,
         options: Vec<(String, Option<String>)>,
     ) -> Self {
-        let engine = match args {
-            None => uci::Engine::new(path).expect("engine should be OK"),
-            Some(args) => uci::Engine::with_args(path, args).expect("engine should be OK"),
-        };
+        let play_multi_pv = options
+            .iter()
+            .find(|(id, _)| id == "MultiPV")
+            .and_then(|(_, value)| value.as_ref())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1);
         UciEngine {
             rx,
             tx,
             engine,
             options,
+            declared: Vec::new(),
+            deferred: Vec::new(),
+            search: None,
+            play_multi_pv,
+            multi_pv: play_multi_pv,
+            strength: None,
+            limited_elo: None,
+            pending: VecDeque::new(),
+            closed: false,
+            quit: false,
         }
     }
 
-    fn send_id(&self) {
-        let name = self
-            .engine
-            .command_and_wait_for("uci", "id name")
-            .map(|lines| {
-                for line in lines.split("\n") {
-                    if let Ok(UciMessage::Id { name, .. }) = UciMessage::from_str(line) {
-                        if let Some(name) = name {
-                            return name;
-                        }
-                    } else {
-                        log::debug!("<engine> {line}");
+    fn send(&mut self, command: &str) {
+        if let Err(err) = self.engine.send(command) {
+            log::error!("<uci-engine> failed to send '{command}': {err}");
+        }
+    }
+
+    /// Read the engine's output up to a line starting with `prefix`, `None`
+    /// if it doesn't come in time.
+    ///
+    /// Commands coming in meanwhile are kept for later.
+    fn wait_for(&mut self, prefix: &str) -> Option<Vec<String>> {
+        let deadline = Instant::now() + ANSWER_TIMEOUT;
+        let mut lines = Vec::new();
+        loop {
+            match self
+                .rx
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(Input::Line(line)) => {
+                    let done = line.starts_with(prefix);
+                    lines.push(line);
+                    if done {
+                        break;
                     }
                 }
-                "UCI Engine".to_string()
-            })
-            .unwrap_or("UCI Engine".to_string());
+                Ok(Input::Command(command)) => self.pending.push_back(command),
+                Ok(Input::Closed) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(Input::Quit) | Err(RecvTimeoutError::Disconnected) => {
+                    self.quit = true;
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    log::error!("<uci-engine> no {prefix} from the engine");
+                    return None;
+                }
+            }
+        }
+        Some(lines)
+    }
 
-        let _ = self.tx.send(EngineMessage::Id(name));
+    /// Introduce ourselves, an engine leaving or not answering isn't one.
+    fn send_id(&mut self) -> Result<(), EngineError> {
+        self.send("uci");
+        let Some(lines) = self.wait_for("uciok") else {
+            return Err(EngineError::NotUci);
+        };
+        if self.closed {
+            return Err(EngineError::Died);
+        }
+        let mut name = None;
+        for line in lines.iter() {
+            match UciMessage::from_str(line) {
+                Ok(UciMessage::Id {
+                    name: Some(id_name),
+                    ..
+                }) => name = Some(id_name),
+                Ok(UciMessage::Option(config)) => self.declared.push(config.into()),
+                _ => {}
+            }
+        }
+
+        let _ = self.tx.send(EngineMessage::Id {
+            name: name.unwrap_or("UCI Engine".to_string()),
+            options: self.declared.clone(),
+        });
+        Ok(())
     }
 
-    fn set_options(&self) {
-        for (id, value) in self.options.iter() {
-            let _ = self
-                .engine
-                .set_option(id, &value.clone().unwrap_or(String::new()));
+    fn set_options(&mut self) {
+        for (id, value) in self.options.clone() {
+            self.apply_option(id, value);
+        }
+        self.send("isready");
+        let _ = self.wait_for("readyok");
+    }
+
+    /// Options only go to an idle engine: after the move being searched,
+    /// or stopping the analysis to start it again.
+    fn set_option(&mut self, name: String, value: Option<String>) {
+        match &self.search {
+            Some(Search {
+                kind: SearchKind::BestMove { .. },
+                ..
+            }) => self.deferred.push((name, value)),
+            Some(Search {
+                kind: SearchKind::Analysis,
+                id,
+                fen_string,
+                ..
+            }) => {
+                let (id, fen_string) = (*id, fen_string.clone());
+                self.stop_search();
+                self.apply_option(name, value);
+                // as many lines as before, unless it's MultiPV that changed
+                self.analyse(id, fen_string, self.multi_pv);
+            }
+            None => self.apply_option(name, value),
         }
     }
 
-    fn start(&self) {
+    /// Send an option if the engine declares it and takes the value
+    fn apply_option(&mut self, name: String, value: Option<String>) {
+        let checked = match find_option(&self.declared, &name) {
+            Some(option) => option
+                .validate(value.as_deref())
+                .map(|_| option.name.clone()),
+            None => Err(format!("unknown option {name}")),
+        };
+        let name = match checked {
+            Ok(name) => name,
+            Err(message) => {
+                log::error!("<uci-engine> {message}");
+                let _ = self
+                    .tx
+                    .send(EngineMessage::Error(EngineError::InvalidOption { message }));
+                return;
+            }
+        };
+        match &value {
+            Some(value) => self.send(&format!("setoption name {name} value {value}")),
+            None => self.send(&format!("setoption name {name}")),
+        }
+        if name == "MultiPV"
+            && let Some(multi_pv) = value.and_then(|value| value.trim().parse().ok())
+        {
+            self.play_multi_pv = multi_pv;
+            self.multi_pv = multi_pv;
+        }
+    }
+
+    fn apply_deferred(&mut self) {
+        for (name, value) in std::mem::take(&mut self.deferred) {
+            self.apply_option(name, value);
+        }
+    }
+
+    fn start(&mut self) {
         self.set_options();
 
         loop {
-            match self.rx.recv() {
-                Err(err) => {
-                    log::error!("Engine channel error: {}", err);
-                    break;
-                }
-                Ok(msg) => match msg {
-                    EngineCommand::NewGame => self.new_game(),
-                    EngineCommand::Go { fen, depth } => self.go(fen, depth),
-                    EngineCommand::Stop => break,
+            if self.quit {
+                break;
+            }
+            if self.closed {
+                log::error!("<uci-engine> engine exited");
+                let _ = self.tx.send(EngineMessage::Error(EngineError::Died));
+                break;
+            }
+            let input = match self.pending.pop_front() {
+                Some(command) => Input::Command(command),
+                None => match self.next_input() {
+                    Ok(input) => input,
+                    Err(err) => {
+                        log::error!("Engine channel error: {}", err);
+                        break;
+                    }
                 },
+            };
+            match input {
+                Input::Command(EngineCommand::NewGame) => self.new_game(),
+                Input::Command(EngineCommand::Go { id, fen, limits }) => self.go(id, fen, limits),
+                Input::Command(EngineCommand::Analyse { id, fen, multi_pv }) => {
+                    self.analyse(id, fen, multi_pv)
+                }
+                Input::Command(EngineCommand::Stop) => self.stop_search(),
+                Input::Command(EngineCommand::SetOption { name, value }) => {
+                    self.set_option(name, value)
+                }
+                Input::Command(EngineCommand::SetStrength { strength }) => self.strength = strength,
+                Input::Line(line) => self.read_line(&line),
+                Input::Closed => self.closed = true,
+                Input::Quit => break,
+            }
+        }
+    }
+
+    /// Our next command or the engine's next line, stopping an endless
+    /// search for its move once its time is up.
+    fn next_input(&mut self) -> Result<Input, RecvError> {
+        loop {
+            let Some(stop_at) = self.search.as_ref().and_then(|search| search.stop_at) else {
+                return self.rx.recv();
+            };
+            match self
+                .rx
+                .recv_timeout(stop_at.saturating_duration_since(Instant::now()))
+            {
+                Ok(input) => return Ok(input),
+                Err(RecvTimeoutError::Timeout) => {
+                    // the search is kept, for its best move to be played
+                    if let Some(search) = self.search.as_mut() {
+                        search.stop_at = None;
+                    }
+                    self.send("stop");
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(RecvError),
             }
         }
     }
 
-    pub fn update_move(&self, best_move_uci: UciMove, game: Chess, score: Score) {
+    /// Stop the running search, if any, and wait for it to be over
+    fn stop_search(&mut self) {
+        if self.search.take().is_some() {
+            self.send("stop");
+            let _ = self.wait_for("bestmove");
+        }
+        self.apply_deferred();
+    }
+
+    fn read_line(&mut self, line: &str) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        match UciMessage::from_str(line) {
+            Ok(UciMessage::Info(info)) => {
+                if info.score.is_none() || info.pv.is_empty() {
+                    return;
+                }
+                search.lines.insert(info.multi_pv.unwrap_or(1), info);
+                if let SearchKind::Analysis = search.kind {
+                    let _ = self.tx.send(EngineMessage::Info {
+                        id: search.id,
+                        fen: search.fen_string.clone(),
+                        lines: get_lines(&search.lines),
+                    });
+                }
+            }
+            Ok(UciMessage::BestMove { best_move, .. }) => {
+                if let Some(Search {
+                    id,
+                    fen_string,
+                    fen,
+                    kind: SearchKind::BestMove { margin },
+                    lines,
+                    ..
+                }) = self.search.take()
+                {
+                    match Chess::from_setup(
+                        fen.as_setup().clone(),
+                        shakmaty::CastlingMode::Standard,
+                    ) {
+                        Ok(game) => {
+                            let (best_move, score) =
+                                match margin.and_then(|margin| pick_candidate(&lines, margin)) {
+                                    Some(candidate) => candidate,
+                                    None => {
+                                        let score = get_score(&lines, &best_move);
+                                        (best_move, score)
+                                    }
+                                };
+                            self.update_move(id, fen_string, best_move, game, score);
+                            self.apply_deferred();
+                        }
+                        Err(err) => {
+                            log::error!("<uci-engine> searched an invalid position: {err}");
+                            let _ =
+                                self.tx
+                                    .send(EngineMessage::Error(EngineError::InvalidPosition {
+                                        fen: fen_string,
+                                    }));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn update_move(
+        &self,
+        id: SearchId,
+        fen: String,
+        best_move_uci: UciMove,
+        game: Chess,
+        score: Score,
+    ) {
         match best_move_uci.to_move(&game) {
-            Err(e) => log::error!(
-                "<uci-engine> Failed to produce a bestmove from {best_move_uci}: {} ",
-                e,
-            ),
+            Err(e) => {
+                log::error!(
+                    "<uci-engine> Failed to produce a bestmove from {best_move_uci}: {} ",
+                    e,
+                );
+                let _ = self.tx.send(EngineMessage::Error(EngineError::IllegalMove {
+                    fen,
+                    move_: best_move_uci.to_string(),
+                }));
+            }
             Ok(m) => {
                 let _ = self.tx.send(EngineMessage::BestMove {
+                    id,
+                    fen,
                     move_: m.into(),
                     score,
                 });
@@ -101,184 +408,217 @@ impl UciEngine {
         }
     }
 
-    fn new_game(&self) {
-        let _ = self
-            .engine
-            .command_with_duration("ucinewgame", std::time::Duration::from_millis(100));
+    fn new_game(&mut self) {
+        self.stop_search();
+        self.send("ucinewgame");
+        self.send("isready");
+        let _ = self.wait_for("readyok");
     }
 
-    fn go(&self, fen_string: String, depth: u8) {
+    fn start_search(&mut self, id: SearchId, fen_string: String, go: UciMessage, kind: SearchKind) {
+        self.stop_search();
         if let Ok(fen) = Fen::from_str(&fen_string) {
             let setpos = shakmaty_uci::UciMessage::Position {
                 startpos: false,
                 fen: Some(fen.clone()),
                 moves: Vec::new(),
             };
-            let goc = shakmaty_uci::UciMessage::Go {
-                // time_control: Some(shakmaty_uci::UciTimeControl::TimeLeft {
-                //     white_time: Some(white_time.to_std().expect("positive duration")),
-                //     black_time: Some(black_time.to_std().expect("positive duration")),
-                //     white_increment: None,
-                //     black_increment: None,
-                //     moves_to_go: None,
-                // }),
-                time_control: None,
-                search_control: Some(shakmaty_uci::UciSearchControl::depth(depth)),
-            };
-            if self.engine.command(&setpos.to_string()).is_ok() {
-                let _ = self
-                    .engine
-                    .command_and_wait_for(&goc.to_string(), "bestmove")
-                    .map(|lines| {
-                        let mut infos: Vec<UciInfo> = Vec::new();
-                        for line in lines.split("\n") {
-                            if let Ok(UciMessage::BestMove { best_move, .. }) =
-                                UciMessage::from_str(line)
-                            {
-                                let game = Chess::from_setup(
-                                    fen.as_setup().clone(),
-                                    shakmaty::CastlingMode::Standard,
-                                )
-                                .expect("argh!");
-
-                                let score = get_score(&infos, game.turn(), &best_move);
-
-                                self.update_move(best_move, game, score);
-                            } else if let Ok(UciMessage::Info(info)) = UciMessage::from_str(line) {
-                                infos.push(info);
-                            }
-                        }
-                        "OK".to_string()
-                    });
-            };
+            self.send(&setpos.to_string());
+            self.send(&go.to_string());
+            self.search = Some(Search {
+                id,
+                fen_string,
+                fen,
+                kind,
+                lines: BTreeMap::new(),
+                stop_at: None,
+            });
         } else {
             log::error!("<uci-engine> failed to produce a `Fen` from fen string:  '{fen_string}'");
+            let _ = self
+                .tx
+                .send(EngineMessage::Error(EngineError::InvalidPosition {
+                    fen: fen_string,
+                }));
         }
     }
-}
 
-/// lookup a possible score in infos list
-fn get_score(infos: &Vec<UciInfo>, color: Color, best_move: &UciMove) -> Score {
-    let mut candidates = infos
-        .iter()
-        .filter(|info| {
-            info.score.is_some() // info score will be unwrapable later
-                && info
-                    .pv
-                    .first()
-                    .map(|m| *m == *best_move)
-                    .unwrap_or(false)
-        })
-        .collect::<Vec<_>>();
-    candidates.sort_by_key(|info| info.pv.len());
-    let max_len = candidates
-        .iter()
-        .map(|info| info.pv.len())
-        .max()
-        .unwrap_or(0);
-
-    
-    candidates
-        .into_iter()
-        .filter(|c| c.pv.len() == max_len)
-        .reduce(|acc, info| {
-            match comp_score(
-                acc.score.clone().unwrap(),
-                info.score.clone().unwrap(),
-                color,
-            ) {
-                CompScore::Right => info,
-                _ => acc,
+    fn go(&mut self, id: SearchId, fen_string: String, mut limits: SearchLimits) {
+        let mut multi_pv = self.play_multi_pv;
+        let mut margin = None;
+
+        let elo = self
+            .strength
+            .as_ref()
+            .and_then(|strength| strength.uci_elo(&self.declared));
+        if let Some(strength) = &self.strength
+            && elo.is_none()
+        {
+            // held back by its search, and not always playing its best line
+            if let Some(nodes) = strength.nodes {
+                limits.nodes = Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes)));
             }
-        })
-        .map(|info| Score::from(info.clone()))
-        .unwrap_or(Score::None)
-}
+            if let Some(movetime) = strength.movetime {
+                limits.movetime = Some(
+                    limits
+                        .movetime
+                        .map_or(movetime, |limit| limit.min(movetime)),
+                );
+            }
+            if strength.candidates > 1 {
+                multi_pv = strength.candidates;
+                margin = Some(strength.margin);
+            }
+        }
+        let goc = limits.go_message();
+        self.stop_search();
+        self.set_limited_elo(elo);
+        self.set_multi_pv(multi_pv);
+        self.start_search(id, fen_string, goc, SearchKind::BestMove { margin });
+        if (limits.infinite || limits.ponder)
+            && let Some(search) = self.search.as_mut()
+        {
+            search.stop_at = limits.movetime.map(|movetime| Instant::now() + movetime);
+        }
+    }
 
-enum CompScore {
-    Left,
-    Right,
-    Equal,
-}
+    /// Search until stopped, reporting on the way `multi_pv` lines
+    fn analyse(&mut self, id: SearchId, fen_string: String, multi_pv: u16) {
+        let goc = SearchLimits {
+            infinite: true,
+            ..Default::default()
+        }
+        .go_message();
+        self.stop_search();
+        self.set_limited_elo(None);
+        self.set_multi_pv(multi_pv.max(1));
+        self.start_search(id, fen_string, goc, SearchKind::Analysis);
+    }
 
-fn comp_score(a: UciInfoScore, b: UciInfoScore, color: Color) -> CompScore {
-    use CompScore::*;
-    let gt = if color == Color::Black {
-        |a: i32, b: i32| a < b
-    } else {
-        |a: i32, b: i32| a > b
-    };
-
-    match (a, b) {
-        (UciInfoScore { mate: Some(_), .. }, UciInfoScore { mate: None, .. }) => Left,
-        (UciInfoScore { mate: None, .. }, UciInfoScore { mate: Some(_), .. }) => Right,
-        (UciInfoScore { mate: Some(na), .. }, UciInfoScore { mate: Some(nb), .. }) => {
-            if na > nb {
-                Left
-            } else if na < nb {
-                Right
-            } else {
-                Equal
-            }
+    /// Only while not searching, the engine keeping its strength if never limited
+    fn set_limited_elo(&mut self, elo: Option<i64>) {
+        if elo == self.limited_elo {
+            return;
         }
-        (UciInfoScore { cp: Some(_), .. }, UciInfoScore { cp: None, .. }) => Left,
-        (UciInfoScore { cp: None, .. }, UciInfoScore { cp: Some(_), .. }) => Right,
-        (UciInfoScore { cp: Some(cpa), .. }, UciInfoScore { cp: Some(cpb), .. }) => {
-            if gt(cpa, cpb) {
-                Left
-            } else if !gt(cpa, cpb) {
-                Right
-            } else {
-                Equal
+        match elo {
+            Some(elo) => {
+                if self.limited_elo.is_none() {
+                    self.send("setoption name UCI_LimitStrength value true");
+                }
+                self.send(&format!("setoption name UCI_Elo value {elo}"));
             }
+            None => self.send("setoption name UCI_LimitStrength value false"),
         }
-        // TODO: consider bounds
-        _ => Equal,
+        self.limited_elo = elo;
     }
+
+    /// Only while not searching
+    fn set_multi_pv(&mut self, multi_pv: u16) {
+        if multi_pv != self.multi_pv {
+            self.send(&format!("setoption name MultiPV value {multi_pv}"));
+            self.multi_pv = multi_pv;
+        }
+    }
+}
+
+/// Lines of the search, best first
+fn get_lines(lines: &BTreeMap<u16, UciInfo>) -> Vec<SearchInfo> {
+    lines
+        .values()
+        .map(|info| SearchInfo::from(info.clone()))
+        .collect()
+}
+
+/// lookup the score of the line starting with the best move
+fn get_score(lines: &BTreeMap<u16, UciInfo>, best_move: &UciMove) -> Score {
+    get_lines(lines)
+        .into_iter()
+        .find(|line| line.pv.first() == Some(best_move))
+        .map(|line| line.score)
+        .unwrap_or(Score::None)
 }
 
 pub struct EngineConnection {
-    tx: Sender<EngineCommand>,
-    receiver: Receiver<EngineMessage>,
+    tx: Sender<Input>,
+    // behind a lock so that we can wait for messages and send commands from different threads
+    receiver: Mutex<Receiver<EngineMessage>>,
     engine_id: Option<String>,
+    options: Vec<EngineOption>,
 }
 
 impl EngineConnection {
     fn new(
-        tx: Sender<EngineCommand>,
+        tx: Sender<Input>,
         rx: Receiver<EngineMessage>,
         engine_id: Option<String>,
+        options: Vec<EngineOption>,
     ) -> Self {
         Self {
             tx,
-            receiver: rx,
+            receiver: Mutex::new(rx),
             engine_id,
+            options,
         }
     }
 }
 
+impl Drop for EngineConnection {
+    fn drop(&mut self) {
+        let _ = self.tx.send(Input::Quit);
+    }
+}
+
 impl Engine for EngineConnection {
     fn name(&self) -> String {
         self.engine_id.clone().unwrap_or(String::from("-"))
     }
 
     fn new_game(&self) {
-        let _ = self.tx.send(EngineCommand::NewGame);
+        let _ = self.tx.send(Input::Command(EngineCommand::NewGame));
     }
 
     fn stop(&self) {
-        let _ = self.tx.send(EngineCommand::Stop);
+        let _ = self.tx.send(Input::Command(EngineCommand::Stop));
     }
 
-    fn go(&self, fen_string: String, depth: u8) {
-        let _ = self.tx.send(EngineCommand::Go {
+    fn go(&self, id: SearchId, fen_string: String, limits: SearchLimits) {
+        let _ = self.tx.send(Input::Command(EngineCommand::Go {
+            id,
             fen: fen_string,
-            depth,
-        });
+            limits,
+        }));
+    }
+
+    fn analyse(&self, id: SearchId, fen_string: String, multi_pv: u16) {
+        let _ = self.tx.send(Input::Command(EngineCommand::Analyse {
+            id,
+            fen: fen_string,
+            multi_pv,
+        }));
+    }
+
+    fn options(&self) -> Vec<EngineOption> {
+        self.options.clone()
+    }
+
+    fn set_option(&self, name: String, value: Option<String>) {
+        let _ = self
+            .tx
+            .send(Input::Command(EngineCommand::SetOption { name, value }));
+    }
+
+    fn set_strength(&self, strength: Option<Strength>) {
+        let _ = self
+            .tx
+            .send(Input::Command(EngineCommand::SetStrength { strength }));
     }
 
     fn recv(&self) -> Result<EngineMessage, RecvError> {
-        self.receiver.recv()
+        self.receiver.lock().map_err(|_| RecvError)?.recv()
+    }
+
+    fn quit(&self) {
+        let _ = self.tx.send(Input::Quit);
     }
 }
 
@@ -286,26 +626,32 @@ pub fn connect_engine(
     path: &str,
     args: Option<Vec<String>>,
     options: Vec<(String, Option<String>)>,
-) -> EngineConnection {
-    let (sender_to, receiver_to) = channel::<EngineCommand>();
+) -> Result<EngineConnection, EngineError> {
+    let (sender_to, receiver_to) = channel::<Input>();
     let (sender_from, receiver_from) = channel::<EngineMessage>();
-    let cloned_path = String::from(path);
+    let process =
+        EngineProcess::spawn(path, args, sender_to.clone()).map_err(|err| EngineError::Spawn {
+            path: path.to_string(),
+            message: err.to_string(),
+        })?;
     thread::spawn(move || {
-        let engine = UciEngine::new(&cloned_path, receiver_to, sender_from, args, options);
-        engine.send_id();
-        engine.start();
-    });
-
-    let id = receiver_from
-        .recv()
-        .map(|msg| {
-            if let EngineMessage::Id(id) = msg {
-                id
-            } else {
-                String::from("NN")
+        let mut engine = UciEngine::new(process, receiver_to, sender_from, options);
+        match engine.send_id() {
+            Ok(()) => engine.start(),
+            Err(err) => {
+                let _ = engine.tx.send(EngineMessage::Error(err));
             }
-        })
-        .ok();
+        }
+    });
 
-    EngineConnection::new(sender_to, receiver_from, id)
+    match receiver_from.recv() {
+        Ok(EngineMessage::Id { name, options }) => Ok(EngineConnection::new(
+            sender_to,
+            receiver_from,
+            Some(name),
+            options,
+        )),
+        Ok(EngineMessage::Error(err)) => Err(err),
+        _ => Err(EngineError::Died),
+    }
 }
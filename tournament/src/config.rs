@@ -0,0 +1,247 @@
+use clap::{CommandFactory, Parser, error::ErrorKind};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use ucui_eco::{Eco, get_openings_table, lookup_eco_from_code};
+use ucui_engine::{TimeControl, parse_uci_options};
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct Config {
+    /// Tournament file
+    ///
+    /// A TOML file with the engines taking part, and the settings of the
+    /// tournament named as the command line's options, which override
+    /// them. Each engine can have a time control or a depth of its own.
+    ///
+    /// Example:
+    ///
+    /// games = 4
+    /// time-control = "1+1"
+    /// eco = ["B2", "C6"]
+    /// pgn = "tournament.pgn"
+    ///
+    /// [engines.stockfish]
+    /// path = "/usr/games/stockfish"
+    /// uci-option = ["Threads:2", "Hash:128"]
+    ///
+    /// [engines.fairy]
+    /// path = "/usr/games/fairy-stockfish"
+    /// args = ["--quiet"]
+    /// time-control = "2+1"
+    #[arg(value_name = "FILE", verbatim_doc_comment)]
+    file: PathBuf,
+
+    /// Name of the event, in the games' tags
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Play everyone against this engine, instead of a round robin
+    #[arg(long, value_name = "ENGINE")]
+    gauntlet: Option<String>,
+
+    /// Games of each pairing
+    ///
+    /// Engines play both colors of the same opening, in two games in a row.
+    #[arg(long, value_name = "N")]
+    games: Option<usize>,
+
+    /// Time control of engines without their own
+    ///
+    /// Base time in minutes and increment in seconds. Without a time
+    /// control, engines search to their depth.
+    ///
+    /// Example: --time-control 1+0.5
+    #[arg(long, value_name = "MINUTES+SECONDS")]
+    time_control: Option<TimeControl>,
+
+    /// Depth of engines without their own, when not on the clock
+    #[arg(long, value_name = "DEPTH")]
+    depth: Option<u8>,
+
+    /// ECO codes
+    ///
+    /// Openings are drawn from these, eco code is a pattern, and from
+    /// the whole table without any.
+    ///
+    /// Example: --eco A21 --eco A3
+    #[arg(long)]
+    eco: Vec<String>,
+
+    /// Write the games as PGN, as they're played
+    #[arg(long, value_name = "FILE")]
+    pgn: Option<String>,
+
+    /// Adjudicate a draw after this many half-moves
+    #[arg(long, value_name = "N")]
+    max_plies: Option<usize>,
+}
+
+/// An engine taking part, by its name in the tournament file.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct EngineEntry {
+    path: String,
+    args: Vec<String>,
+    uci_option: Vec<String>,
+    time_control: Option<String>,
+    depth: Option<u8>,
+}
+
+/// The tournament file, its settings named as the command line's options.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct TournamentFile {
+    event: Option<String>,
+    gauntlet: Option<String>,
+    games: Option<usize>,
+    time_control: Option<String>,
+    depth: Option<u8>,
+    eco: Vec<String>,
+    pgn: Option<String>,
+    max_plies: Option<usize>,
+    engines: BTreeMap<String, EngineEntry>,
+}
+
+/// An engine, and how it plays
+pub struct Player {
+    pub name: String,
+    pub path: String,
+    pub args: Option<Vec<String>>,
+    pub options: Vec<(String, Option<String>)>,
+    pub time_control: Option<TimeControl>,
+    pub depth: u8,
+}
+
+pub struct Tournament {
+    pub event: String,
+    pub players: Vec<Player>,
+    /// index of the player meeting all the others
+    pub gauntlet: Option<usize>,
+    pub games: usize,
+    pub openings: Vec<Eco>,
+    pub pgn: Option<String>,
+    pub max_plies: usize,
+}
+
+const DEFAULT_GAMES: usize = 2;
+const DEFAULT_DEPTH: u8 = 12;
+const DEFAULT_MAX_PLIES: usize = 400;
+
+fn parse_time_control(time_control: Option<String>) -> Result<Option<TimeControl>, String> {
+    time_control
+        .map(|time_control| {
+            time_control
+                .parse()
+                .map_err(|err| format!("time-control: {err}"))
+        })
+        .transpose()
+}
+
+/// Openings matching any of the patterns, all of them without any
+fn select_openings(patterns: &[String]) -> Vec<Eco> {
+    if patterns.is_empty() {
+        return get_openings_table();
+    }
+    let mut openings: Vec<Eco> = Vec::new();
+    for pattern in patterns {
+        for eco in lookup_eco_from_code(pattern) {
+            if !openings.iter().any(|known| known.pgn == eco.pgn) {
+                openings.push(eco);
+            }
+        }
+    }
+    openings
+}
+
+impl Config {
+    /// The command line over the tournament file.
+    fn merge(self, file: TournamentFile) -> Result<Tournament, String> {
+        let time_control = match self.time_control {
+            Some(time_control) => Some(time_control),
+            None => parse_time_control(file.time_control)?,
+        };
+        let depth = self.depth.or(file.depth).unwrap_or(DEFAULT_DEPTH);
+
+        let mut players = Vec::new();
+        for (name, entry) in file.engines {
+            if entry.path.is_empty() {
+                return Err(format!("engine {name} has no path"));
+            }
+            let own_time_control = parse_time_control(entry.time_control)
+                .map_err(|err| format!("engine {name}: {err}"))?;
+            players.push(Player {
+                args: (!entry.args.is_empty()).then_some(entry.args),
+                options: parse_uci_options(&entry.uci_option),
+                time_control: own_time_control.or(time_control),
+                depth: entry.depth.unwrap_or(depth),
+                path: entry.path,
+                name,
+            });
+        }
+        if players.len() < 2 {
+            return Err(String::from("a tournament needs at least two engines"));
+        }
+
+        let gauntlet = match self.gauntlet.or(file.gauntlet) {
+            Some(name) => Some(
+                players
+                    .iter()
+                    .position(|player| player.name == name)
+                    .ok_or_else(|| format!("no engine {name} for the gauntlet"))?,
+            ),
+            None => None,
+        };
+
+        let games = self.games.or(file.games).unwrap_or(DEFAULT_GAMES);
+        if games == 0 {
+            return Err(String::from("games: at least one game per pairing"));
+        }
+
+        let eco = if self.eco.is_empty() {
+            file.eco
+        } else {
+            self.eco
+        };
+        let openings = select_openings(&eco);
+        if openings.is_empty() {
+            return Err(format!("no opening for eco {}", eco.join(", ")));
+        }
+
+        Ok(Tournament {
+            event: self
+                .event
+                .or(file.event)
+                .unwrap_or_else(|| String::from("Engine tournament")),
+            players,
+            gauntlet,
+            games,
+            openings,
+            pgn: self.pgn.or(file.pgn),
+            max_plies: self
+                .max_plies
+                .or(file.max_plies)
+                .unwrap_or(DEFAULT_MAX_PLIES),
+        })
+    }
+}
+
+/// The tournament asked for, exiting with an error if it can't be played.
+pub fn load_tournament() -> Tournament {
+    let config = Config::parse();
+    let exit = |message: String| -> ! {
+        Config::command()
+            .error(ErrorKind::InvalidValue, message)
+            .exit()
+    };
+    let path = config.file.clone();
+    let file = fs::read_to_string(&path)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))
+        .and_then(|content| {
+            toml::from_str::<TournamentFile>(&content)
+                .map_err(|err| format!("invalid {}: {err}", path.display()))
+        })
+        .unwrap_or_else(|err| exit(err));
+    config
+        .merge(file)
+        .unwrap_or_else(|err| exit(format!("{}: {err}", path.display())))
+}
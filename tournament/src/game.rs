@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        Arc,
+        mpsc::{Receiver, RecvTimeoutError, channel},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use shakmaty::{Chess, Color, EnPassantMode, Move, Outcome, Position, fen::Fen};
+use ucui_eco::Eco;
+use ucui_engine::{
+    ClockTime, Engine, EngineError, EngineMessage, SearchId, SearchLimits, connect_engine,
+};
+
+use crate::config::Player;
+
+/// How a game came to an end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ending {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    Repetition,
+    FiftyMoves,
+    /// the game went on past the limit of half-moves
+    MoveLimit,
+    TimeForfeit,
+    IllegalMove,
+    /// the engine couldn't start, or died during the game
+    EngineFailure,
+}
+
+impl Ending {
+    /// As the PGN Termination tag has it
+    pub fn termination(&self) -> &'static str {
+        match self {
+            Ending::MoveLimit => "adjudication",
+            Ending::TimeForfeit => "time forfeit",
+            Ending::IllegalMove => "rules infraction",
+            Ending::EngineFailure => "abandoned",
+            _ => "normal",
+        }
+    }
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Ending::Checkmate => "checkmate",
+            Ending::Stalemate => "stalemate",
+            Ending::InsufficientMaterial => "insufficient material",
+            Ending::Repetition => "threefold repetition",
+            Ending::FiftyMoves => "fifty-move rule",
+            Ending::MoveLimit => "move limit",
+            Ending::TimeForfeit => "time forfeit",
+            Ending::IllegalMove => "illegal move",
+            Ending::EngineFailure => "engine failure",
+        };
+        write!(f, "{text}")
+    }
+}
+
+pub struct GameReport {
+    pub moves: Vec<Move>,
+    pub outcome: Outcome,
+    pub ending: Ending,
+}
+
+/// An engine in a game, its messages read on a thread of their own to
+/// be waited on with a timeout.
+struct Side<'a> {
+    player: &'a Player,
+    engine: Arc<dyn Engine + Send + Sync>,
+    rx: Receiver<EngineMessage>,
+    /// time left on the clock, if playing on it
+    remaining: Option<Duration>,
+    /// the last search asked for
+    search: SearchId,
+}
+
+impl<'a> Side<'a> {
+    fn connect(player: &'a Player) -> Result<Self, EngineError> {
+        let engine: Arc<dyn Engine + Send + Sync> = Arc::from(connect_engine(
+            &player.path,
+            player.args.clone(),
+            player.options.clone(),
+        )?);
+        let (tx, rx) = channel();
+        let reader = engine.clone();
+        thread::spawn(move || {
+            while let Ok(message) = reader.recv() {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        engine.new_game();
+        Ok(Self {
+            player,
+            engine,
+            rx,
+            remaining: player.time_control.map(|time_control| time_control.base),
+            search: 0,
+        })
+    }
+
+    fn increment(&self) -> Duration {
+        self.player
+            .time_control
+            .map(|time_control| time_control.increment)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Side<'_> {
+    fn drop(&mut self) {
+        self.engine.quit();
+    }
+}
+
+/// Why a side didn't come up with a move
+enum Failure {
+    Flagged,
+    Illegal,
+    Gone,
+}
+
+/// Ask the side to move for a move in `position`, and wait for it.
+fn get_move(side: &mut Side, other: &Side, position: &Chess) -> Result<Move, Failure> {
+    let fen = Fen::from_position(position.clone(), EnPassantMode::Legal).to_string();
+    let time = side.remaining.map(|remaining| {
+        // an opponent off the clock gets as much time
+        let other_remaining = other.remaining.unwrap_or(remaining);
+        let (white, black) = match position.turn() {
+            Color::White => (
+                (remaining, side.increment()),
+                (other_remaining, other.increment()),
+            ),
+            Color::Black => (
+                (other_remaining, other.increment()),
+                (remaining, side.increment()),
+            ),
+        };
+        ClockTime {
+            white_time: white.0,
+            black_time: black.0,
+            white_increment: white.1,
+            black_increment: black.1,
+        }
+    });
+
+    // on the clock, the engine manages its time rather than going to depth
+    let limits = match time {
+        Some(time) => SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        },
+        None => SearchLimits::depth(side.player.depth),
+    };
+
+    side.search += 1;
+    let started = Instant::now();
+    side.engine.go(side.search, fen.clone(), limits);
+    loop {
+        let message = match side.remaining {
+            Some(remaining) => {
+                let left = remaining.saturating_sub(started.elapsed());
+                side.rx.recv_timeout(left).map_err(|err| match err {
+                    RecvTimeoutError::Timeout => Failure::Flagged,
+                    RecvTimeoutError::Disconnected => Failure::Gone,
+                })?
+            }
+            None => side.rx.recv().map_err(|_| Failure::Gone)?,
+        };
+        match message {
+            EngineMessage::BestMove { id, move_, .. } if id == side.search => {
+                let move_: Move = move_.into();
+                if let Some(remaining) = side.remaining {
+                    let elapsed = started.elapsed();
+                    if elapsed > remaining {
+                        return Err(Failure::Flagged);
+                    }
+                    side.remaining = Some(remaining - elapsed + side.increment());
+                }
+                return if position.is_legal(&move_) {
+                    Ok(move_)
+                } else {
+                    Err(Failure::Illegal)
+                };
+            }
+            EngineMessage::Error(EngineError::IllegalMove { .. }) => {
+                return Err(Failure::Illegal);
+            }
+            EngineMessage::Error(err) if err.is_fatal() => {
+                log::error!("{}: {err}", side.player.name);
+                return Err(Failure::Gone);
+            }
+            EngineMessage::Error(EngineError::InvalidPosition { fen }) => {
+                log::error!("{}: rejected the position {fen}", side.player.name);
+                return Err(Failure::Gone);
+            }
+            EngineMessage::Error(err) => log::warn!("{}: {err}", side.player.name),
+            _ => {}
+        }
+    }
+}
+
+/// The position without its move counters, to spot repetitions
+fn repetition_key(position: &Chess) -> String {
+    Fen::from_position(position.clone(), EnPassantMode::Legal)
+        .to_string()
+        .split(' ')
+        .take(4)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The opening's moves, as far as they go legally
+fn opening_moves(opening: &Eco) -> (Chess, Vec<Move>) {
+    let mut position = Chess::default();
+    let mut moves = Vec::new();
+    for move_ in opening.moves.iter() {
+        let move_: Move = move_.clone().into();
+        if !position.is_legal(&move_) {
+            log::error!("illegal move {move_} in the opening {}", opening.code);
+            break;
+        }
+        position.play_unchecked(&move_);
+        moves.push(move_);
+    }
+    (position, moves)
+}
+
+/// Play a game from the opening, until it's over on the board, on the
+/// clock, or `max_plies` half-moves were played.
+pub fn play_game(white: &Player, black: &Player, opening: &Eco, max_plies: usize) -> GameReport {
+    let (mut position, mut moves) = opening_moves(opening);
+    let forfeit = |moves: Vec<Move>, loser: Color, ending: Ending| GameReport {
+        moves,
+        outcome: Outcome::Decisive { winner: !loser },
+        ending,
+    };
+
+    let mut white_side = match Side::connect(white) {
+        Ok(side) => side,
+        Err(err) => {
+            log::error!("{}: {err}", white.name);
+            return forfeit(moves, Color::White, Ending::EngineFailure);
+        }
+    };
+    let mut black_side = match Side::connect(black) {
+        Ok(side) => side,
+        Err(err) => {
+            log::error!("{}: {err}", black.name);
+            return forfeit(moves, Color::Black, Ending::EngineFailure);
+        }
+    };
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    *seen.entry(repetition_key(&position)).or_default() += 1;
+    loop {
+        if let Some(outcome) = position.outcome() {
+            let ending = if position.is_checkmate() {
+                Ending::Checkmate
+            } else if position.is_stalemate() {
+                Ending::Stalemate
+            } else {
+                Ending::InsufficientMaterial
+            };
+            return GameReport {
+                moves,
+                outcome,
+                ending,
+            };
+        }
+        let draw = if seen.values().any(|count| *count >= 3) {
+            Some(Ending::Repetition)
+        } else if position.halfmoves() >= 100 {
+            Some(Ending::FiftyMoves)
+        } else if moves.len() >= max_plies {
+            Some(Ending::MoveLimit)
+        } else {
+            None
+        };
+        if let Some(ending) = draw {
+            return GameReport {
+                moves,
+                outcome: Outcome::Draw,
+                ending,
+            };
+        }
+
+        let turn = position.turn();
+        let (side, other) = match turn {
+            Color::White => (&mut white_side, &black_side),
+            Color::Black => (&mut black_side, &white_side),
+        };
+        match get_move(side, other, &position) {
+            Ok(move_) => {
+                position.play_unchecked(&move_);
+                moves.push(move_);
+                *seen.entry(repetition_key(&position)).or_default() += 1;
+            }
+            Err(Failure::Flagged) => {
+                // no mate to fear from a lone king
+                let outcome = if position.has_insufficient_material(!turn) {
+                    Outcome::Draw
+                } else {
+                    Outcome::Decisive { winner: !turn }
+                };
+                return GameReport {
+                    moves,
+                    outcome,
+                    ending: Ending::TimeForfeit,
+                };
+            }
+            Err(Failure::Illegal) => return forfeit(moves, turn, Ending::IllegalMove),
+            Err(Failure::Gone) => return forfeit(moves, turn, Ending::EngineFailure),
+        }
+    }
+}
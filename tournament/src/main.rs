@@ -0,0 +1,149 @@
+mod config;
+mod game;
+mod table;
+
+use std::{fs::File, io::Write};
+
+use rand::seq::IndexedRandom;
+use shakmaty::Chess;
+use ucui_eco::Eco;
+use ucui_utils::PgnGame;
+
+use crate::{
+    config::{Tournament, load_tournament},
+    game::{GameReport, play_game},
+    table::Table,
+};
+
+/// Pairs of players meeting, the gauntlet's player first
+fn pairings(tournament: &Tournament) -> Vec<(usize, usize)> {
+    let count = tournament.players.len();
+    match tournament.gauntlet {
+        Some(gauntlet) => (0..count)
+            .filter(|other| *other != gauntlet)
+            .map(|other| (gauntlet, other))
+            .collect(),
+        None => (0..count)
+            .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+            .collect(),
+    }
+}
+
+/// Base and increment in seconds, with their fraction if any
+fn time_control_tag(tournament: &Tournament, player: usize) -> String {
+    match tournament.players[player].time_control {
+        Some(time_control) => format!(
+            "{}+{}",
+            time_control.base.as_secs_f64(),
+            time_control.increment.as_secs_f64()
+        ),
+        None => String::from("-"),
+    }
+}
+
+fn game_pgn(
+    tournament: &Tournament,
+    round: usize,
+    (white, black): (usize, usize),
+    opening: &Eco,
+    report: &GameReport,
+) -> PgnGame {
+    let mut pgn = PgnGame::new(Chess::default(), report.moves.clone());
+    pgn.set_tag("Event", tournament.event.clone());
+    pgn.set_tag("Date", chrono::Local::now().format("%Y.%m.%d").to_string());
+    pgn.set_tag("Round", round.to_string());
+    pgn.set_tag("White", tournament.players[white].name.clone());
+    pgn.set_tag("Black", tournament.players[black].name.clone());
+    pgn.set_tag("Result", report.outcome.to_string());
+    pgn.set_tag("ECO", opening.code.clone());
+    pgn.set_tag("Opening", opening.name.clone());
+    // the tag is for both sides, unless they play on different clocks
+    let (white_time, black_time) = (
+        time_control_tag(tournament, white),
+        time_control_tag(tournament, black),
+    );
+    if white_time == black_time {
+        pgn.set_tag("TimeControl", white_time);
+    } else {
+        pgn.set_tag("WhiteTimeControl", white_time);
+        pgn.set_tag("BlackTimeControl", black_time);
+    }
+    pgn.set_tag("Termination", report.ending.termination());
+    pgn
+}
+
+/// Play all the games, returning the results.
+fn run(tournament: &Tournament) -> Result<Table, String> {
+    let mut pgn_file = match &tournament.pgn {
+        Some(path) => {
+            Some(File::create(path).map_err(|err| format!("failed to create {path}: {err}"))?)
+        }
+        None => None,
+    };
+    let mut table = Table::new(
+        tournament
+            .players
+            .iter()
+            .map(|player| player.name.clone())
+            .collect(),
+    );
+
+    let pairings = pairings(tournament);
+    let total = pairings.len() * tournament.games;
+    let mut round = 0;
+    for (a, b) in pairings {
+        let mut opening = None;
+        for game in 0..tournament.games {
+            // both colors of an opening, in two games in a row
+            if game % 2 == 0 {
+                opening = tournament.openings.choose(&mut rand::rng());
+            }
+            let Some(opening) = opening else {
+                return Err(String::from("no opening to play"));
+            };
+            let (white, black) = if game % 2 == 0 { (a, b) } else { (b, a) };
+            round += 1;
+
+            let report = play_game(
+                &tournament.players[white],
+                &tournament.players[black],
+                opening,
+                tournament.max_plies,
+            );
+            println!(
+                "[{round}/{total}] {} - {}  {}  {} ({}, {} {})",
+                tournament.players[white].name,
+                tournament.players[black].name,
+                report.outcome,
+                report.ending,
+                report.moves.len(),
+                opening.code,
+                opening.name,
+            );
+            table.record(white, black, report.outcome);
+
+            if let Some(file) = pgn_file.as_mut() {
+                let pgn = game_pgn(tournament, round, (white, black), opening, &report);
+                writeln!(file, "{pgn}")
+                    .map_err(|err| format!("failed to write the game: {err}"))?;
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn main() {
+    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+
+    let tournament = load_tournament();
+    match run(&tournament) {
+        Ok(table) => {
+            println!();
+            print!("{table}");
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::{self, Write};
+
+use shakmaty::Outcome;
+
+/// Results between each pair of players, in half-points so that they add up.
+pub struct Table {
+    names: Vec<String>,
+    /// half-points scored by a player against another, and games played
+    results: Vec<Vec<(u32, u32)>>,
+}
+
+/// Half-points as points, "2.5" or "3"
+fn points(half_points: u32) -> String {
+    if half_points.is_multiple_of(2) {
+        format!("{}", half_points / 2)
+    } else {
+        format!("{}.5", half_points / 2)
+    }
+}
+
+/// Rating difference matching a score, as a fraction of the points
+fn elo_difference(score: f64) -> String {
+    if score <= 0.0 {
+        String::from("-inf")
+    } else if score >= 1.0 {
+        String::from("+inf")
+    } else {
+        // adding zero turns a rounded -0 into 0
+        let difference = (-400.0 * (1.0 / score - 1.0).log10()).round() + 0.0;
+        format!("{difference:+}")
+    }
+}
+
+impl Table {
+    pub fn new(names: Vec<String>) -> Self {
+        let count = names.len();
+        Self {
+            names,
+            results: vec![vec![(0, 0); count]; count],
+        }
+    }
+
+    pub fn record(&mut self, white: usize, black: usize, outcome: Outcome) {
+        let (white_points, black_points) = match outcome.winner() {
+            Some(shakmaty::Color::White) => (2, 0),
+            Some(shakmaty::Color::Black) => (0, 2),
+            None => (1, 1),
+        };
+        let cell = &mut self.results[white][black];
+        *cell = (cell.0 + white_points, cell.1 + 1);
+        let cell = &mut self.results[black][white];
+        *cell = (cell.0 + black_points, cell.1 + 1);
+    }
+
+    /// Half-points and games of a player
+    fn total(&self, player: usize) -> (u32, u32) {
+        self.results[player]
+            .iter()
+            .fold((0, 0), |(points, games), cell| {
+                (points + cell.0, games + cell.1)
+            })
+    }
+
+    /// Players from first to last
+    fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.names.len()).collect();
+        // by score, then by points for equal scores
+        ranking.sort_by(|a, b| {
+            let (a_points, a_games) = self.total(*a);
+            let (b_points, b_games) = self.total(*b);
+            let a_score = a_points as f64 / a_games.max(1) as f64;
+            let b_score = b_points as f64 / b_games.max(1) as f64;
+            b_score.total_cmp(&a_score).then(b_points.cmp(&a_points))
+        });
+        ranking
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranking = self.ranking();
+        let width = self
+            .names
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or_default()
+            .max(6);
+
+        writeln!(
+            f,
+            "{:>4}  {:<width$}  {:>6}  {:>5}  {:>6}  {:>5}",
+            "Rank", "Engine", "Points", "Games", "Score", "Elo"
+        )?;
+        for (rank, player) in ranking.iter().enumerate() {
+            let (half_points, games) = self.total(*player);
+            let score = half_points as f64 / 2.0 / games.max(1) as f64;
+            writeln!(
+                f,
+                "{:>4}  {:<width$}  {:>6}  {:>5}  {:>5.1}%  {:>5}",
+                rank + 1,
+                self.names[*player],
+                points(half_points),
+                games,
+                score * 100.0,
+                if games == 0 {
+                    String::from("-")
+                } else {
+                    elo_difference(score)
+                },
+            )?;
+        }
+        writeln!(f)?;
+
+        // each row against the columns, in the order of the ranking
+        let mut header = format!("{:>4}  {:<width$}", "", "Engine");
+        for column in 1..=ranking.len() {
+            let _ = write!(header, "  {column:>7}");
+        }
+        writeln!(f, "{header}")?;
+        for (rank, player) in ranking.iter().enumerate() {
+            let mut line = format!("{:>4}  {:<width$}", rank + 1, self.names[*player]);
+            for opponent in ranking.iter() {
+                let (half_points, games) = self.results[*player][*opponent];
+                let cell = if player == opponent {
+                    String::from("*")
+                } else if games == 0 {
+                    String::from("-")
+                } else {
+                    format!("{}/{games}", points(half_points))
+                };
+                let _ = write!(line, "  {cell:>7}");
+            }
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
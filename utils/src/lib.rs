@@ -1,5 +1,7 @@
 pub mod oddity;
+pub mod pgn;
 pub mod serde;
 
 pub use oddity::*;
+pub use pgn::*;
 pub use serde::*;
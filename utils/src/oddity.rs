@@ -14,7 +14,7 @@ pub fn move_classic_to(move_: &Move) -> Square {
     }
 }
 
-pub fn ucimovelist_to_sanlist(mut game: Chess, movelist: &Vec<UciMove>) -> Vec<String> {
+pub fn ucimovelist_to_sanlist(mut game: Chess, movelist: &[UciMove]) -> Vec<String> {
     let mut result = Vec::with_capacity(movelist.len());
     for uci_move in movelist {
         if let Ok(move_) = uci_move.to_move(&game) {
@@ -0,0 +1,462 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+use shakmaty::{CastlingMode, Chess, Color, EnPassantMode, Move, Position, fen::Fen, san::SanPlus};
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const LINE_WIDTH: usize = 80;
+
+/// Suffixes standing for the first six NAGs, as in "e4!" for "e4 $1".
+const MOVE_SUFFIXES: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// NAGs and comments found after a move of the mainline, `ply` being
+/// the number of moves played up to and including it.
+#[derive(Clone, Default, Debug)]
+pub struct MoveAnnotation {
+    pub ply: usize,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+}
+
+/// A game as found in a PGN file: its tags and the mainline from
+/// a starting position.
+#[derive(Clone, Default)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Chess,
+    pub moves: Vec<Move>,
+    pub annotations: Vec<MoveAnnotation>,
+}
+
+impl PgnGame {
+    pub fn new(start: Chess, moves: Vec<Move>) -> Self {
+        Self {
+            tags: Vec::new(),
+            start,
+            moves,
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Annotation of the move leading to the position after `ply` moves.
+    pub fn annotation(&self, ply: usize) -> Option<&MoveAnnotation> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.ply == ply)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag<N, V>(&mut self, name: N, value: V)
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name: String = name.into();
+        let value: String = value.into();
+        if let Some(tag) = self.tags.iter_mut().find(|(tag_name, _)| *tag_name == name) {
+            tag.1 = value;
+        } else {
+            self.tags.push((name, value));
+        }
+    }
+
+    pub fn result(&self) -> &str {
+        self.tag("Result").unwrap_or("*")
+    }
+
+    fn movetext(&self) -> Vec<String> {
+        let mut tokens = Vec::with_capacity(self.moves.len() * 2 + 1);
+        let mut pos = self.start.clone();
+        let mut commented = false;
+        for (i, move_) in self.moves.iter().enumerate() {
+            let number = pos.fullmoves();
+            if pos.turn() == Color::White {
+                tokens.push(format!("{number}."));
+            } else if i == 0 || commented {
+                tokens.push(format!("{number}..."));
+            }
+            tokens.push(SanPlus::from_move_and_play_unchecked(&mut pos, move_).to_string());
+
+            commented = false;
+            if let Some(annotation) = self.annotation(i + 1) {
+                tokens.extend(annotation.nags.iter().map(|nag| format!("${nag}")));
+                if let Some(comment) = &annotation.comment {
+                    tokens.push(format!("{{{comment}}}"));
+                    commented = true;
+                }
+            }
+        }
+        tokens.push(self.result().to_string());
+        tokens
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = self.tag(name).unwrap_or(default);
+            writeln!(f, "[{name} \"{}\"]", escape(value))?;
+        }
+        if self.start != Chess::default() {
+            let fen = Fen::from_position(self.start.clone(), EnPassantMode::Legal);
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{fen}\"]")?;
+        }
+        for (name, value) in self.tags.iter() {
+            let reserved =
+                SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name) || name == "SetUp" || name == "FEN";
+            if !reserved {
+                writeln!(f, "[{name} \"{}\"]", escape(value))?;
+            }
+        }
+        writeln!(f)?;
+
+        let mut line = String::new();
+        for token in self.movetext() {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+/// Failure to read a PGN, with the line where it happened.
+#[derive(Debug, Clone)]
+pub struct PgnError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    games: Vec<PgnGame>,
+    game: PgnGame,
+    // set once the movetext of the current game started
+    position: Option<Chess>,
+    variation_depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            line: 1,
+            games: Vec::new(),
+            game: PgnGame::default(),
+            position: None,
+            variation_depth: 0,
+        }
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> PgnError {
+        PgnError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next_char();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next_char() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn read_comment(&mut self) -> Result<String, PgnError> {
+        let line = self.line;
+        let mut comment = String::new();
+        while let Some(c) = self.next_char() {
+            if c == '}' {
+                return Ok(comment.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+            comment.push(c);
+        }
+        Err(PgnError {
+            line,
+            message: String::from("unterminated comment"),
+        })
+    }
+
+    fn read_symbol(&mut self, first: char) -> String {
+        let mut symbol = String::from(first);
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() || "{}()[];$\"".contains(*c) {
+                break;
+            }
+            symbol.push(*c);
+            self.next_char();
+        }
+        symbol
+    }
+
+    fn read_tag(&mut self) -> Result<(), PgnError> {
+        if self.variation_depth > 0 {
+            return Err(self.error("tag pair inside a variation"));
+        }
+        if self.position.is_some() {
+            // previous game had no result
+            self.finish_game();
+        }
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() || *c == '"' || *c == ']' {
+                break;
+            }
+            name.push(*c);
+            self.next_char();
+        }
+        if name.is_empty() {
+            return Err(self.error("tag pair without a name"));
+        }
+        self.skip_whitespace();
+        if self.next_char() != Some('"') {
+            return Err(self.error(format!("missing value for tag {name}")));
+        }
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => {
+                    return Err(self.error(format!("unterminated value for tag {name}")));
+                }
+                Some('"') => break,
+                Some('\\') => match self.chars.next() {
+                    None | Some('\n') => {
+                        return Err(self.error(format!("unterminated value for tag {name}")));
+                    }
+                    Some(c) => value.push(c),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+        self.skip_whitespace();
+        if self.next_char() != Some(']') {
+            return Err(self.error(format!("unterminated tag {name}")));
+        }
+        self.game.set_tag(name, value);
+        Ok(())
+    }
+
+    /// Annotation of the last move of the mainline, if we're right after it.
+    fn annotation(&mut self) -> Option<&mut MoveAnnotation> {
+        if self.variation_depth > 0 || self.position.is_none() || self.game.moves.is_empty() {
+            return None;
+        }
+        let ply = self.game.moves.len();
+        let annotations = &mut self.game.annotations;
+        if annotations
+            .last()
+            .is_none_or(|annotation| annotation.ply != ply)
+        {
+            annotations.push(MoveAnnotation {
+                ply,
+                ..Default::default()
+            });
+        }
+        annotations.last_mut()
+    }
+
+    /// A NAG that isn't one is left out, as is one in a variation.
+    fn read_nag(&mut self, symbol: &str) {
+        let Ok(nag) = symbol[1..].parse::<u8>() else {
+            log::warn!("line {}: invalid NAG '{symbol}' skipped", self.line);
+            return;
+        };
+        if let Some(annotation) = self.annotation() {
+            annotation.nags.push(nag);
+        }
+    }
+
+    fn start_position(&self) -> Result<Chess, PgnError> {
+        match self.game.tag("FEN") {
+            None => Ok(Chess::default()),
+            Some(fen) => Fen::from_ascii(fen.as_bytes())
+                .map_err(|err| self.error(format!("invalid FEN '{fen}': {err}")))?
+                .into_position(CastlingMode::Standard)
+                .map_err(|err| self.error(format!("invalid FEN '{fen}': {err}"))),
+        }
+    }
+
+    fn read_move(&mut self, token: &str) -> Result<(), PgnError> {
+        // castling written with zeros
+        let token = if token.starts_with("0-0") {
+            token.replace('0', "O")
+        } else {
+            token.to_string()
+        };
+        // move numbers, possibly glued to the move as in "12.e4", and the
+        // dots of a black move on its own as in "12. ... Nf6"
+        let rest = token
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches('.');
+        let san = rest.trim_end_matches(['!', '?']);
+        let suffix = &rest[san.len()..];
+        if san.is_empty() {
+            return Ok(());
+        }
+        let san = SanPlus::from_ascii(san.as_bytes())
+            .map_err(|_| self.error(format!("invalid move '{token}'")))?;
+
+        let position = match self.position.take() {
+            Some(position) => position,
+            None => self.start_position()?,
+        };
+        let move_ = san
+            .san
+            .to_move(&position)
+            .map_err(|err| self.error(format!("{err} '{token}'")))?;
+        self.position = Some(
+            position
+                .play(&move_)
+                .map_err(|_| self.error(format!("illegal move '{token}'")))?,
+        );
+        self.game.moves.push(move_);
+        if let Some((_, nag)) = MOVE_SUFFIXES.iter().find(|(s, _)| *s == suffix)
+            && let Some(annotation) = self.annotation()
+        {
+            annotation.nags.push(*nag);
+        }
+        Ok(())
+    }
+
+    fn read_token(&mut self, token: &str) -> Result<(), PgnError> {
+        if self.variation_depth > 0 {
+            Ok(())
+        } else if RESULTS.contains(&token) {
+            if self.game.tag("Result").is_none() {
+                self.game.set_tag("Result", token);
+            }
+            self.finish_game();
+            Ok(())
+        } else {
+            self.read_move(token)
+        }
+    }
+
+    fn finish_game(&mut self) {
+        if self.position.is_some() || !self.game.tags.is_empty() {
+            if let Ok(start) = self.start_position() {
+                self.game.start = start;
+            }
+            self.games.push(std::mem::take(&mut self.game));
+        }
+        self.position = None;
+    }
+
+    fn read(mut self) -> Result<Vec<PgnGame>, PgnError> {
+        let mut line_start = true;
+        while let Some(c) = self.next_char() {
+            match c {
+                ';' => {
+                    self.skip_line();
+                    line_start = true;
+                    continue;
+                }
+                '%' if line_start => {
+                    self.skip_line();
+                    line_start = true;
+                    continue;
+                }
+                '[' => self.read_tag()?,
+                '{' => {
+                    let comment = self.read_comment()?;
+                    if !comment.is_empty()
+                        && let Some(annotation) = self.annotation()
+                    {
+                        annotation.comment = Some(match annotation.comment.take() {
+                            Some(previous) => format!("{previous} {comment}"),
+                            None => comment,
+                        });
+                    }
+                }
+                '(' => self.variation_depth += 1,
+                ')' => {
+                    if self.variation_depth == 0 {
+                        return Err(self.error("unbalanced ')'"));
+                    }
+                    self.variation_depth -= 1;
+                }
+                '$' => {
+                    let nag = self.read_symbol(c);
+                    self.read_nag(&nag);
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let token = self.read_symbol(c);
+                    self.read_token(&token)?;
+                }
+            }
+            line_start = c == '\n';
+        }
+        if self.variation_depth > 0 {
+            return Err(self.error("unterminated variation"));
+        }
+        self.finish_game();
+        Ok(self.games)
+    }
+}
+
+/// Read all games from a PGN string.
+///
+/// Variations are skipped, only mainlines are kept along with the NAGs
+/// and comments of their moves.
+pub fn parse_pgn(input: &str) -> Result<Vec<PgnGame>, PgnError> {
+    Reader::new(input).read()
+}